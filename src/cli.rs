@@ -1,7 +1,52 @@
 use std::path::PathBuf;
 
-use clap_derive::Parser;
+use clap_derive::{Args, Parser, Subcommand};
+
+use crate::data::{
+    import_format::ImportFormat,
+    result_format::{BlobEncoding, ResultFormat},
+};
 #[derive(Parser, Debug)]
 pub struct CommandLine {
     pub file_path: PathBuf,
+    /// Open the database read-only and refuse statements that write
+    #[arg(long)]
+    pub readonly: bool,
+    /// Create the database file when it doesn't exist
+    #[arg(long, conflicts_with = "readonly")]
+    pub create: bool,
+    /// Run a query and print its result instead of starting the TUI. Use `-` to read it from stdin
+    #[arg(short, long)]
+    pub query: Option<String>,
+    /// Output format used with --query
+    #[arg(short, long, value_enum, default_value_t = ResultFormat::TABLE)]
+    pub format: ResultFormat,
+    /// Table the sql format inserts into, by default the one the query reads
+    #[arg(long)]
+    pub table: Option<String>,
+    /// How blobs are written with --query
+    #[arg(long, value_enum, default_value_t = BlobEncoding::HEX)]
+    pub blobs: BlobEncoding,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum Command {
+    /// Import a CSV, TSV or JSON file into a table, creating the table when it doesn't exist
+    #[command(name = "import")]
+    IMPORT(ImportArguments),
+}
+
+#[derive(Args, Debug)]
+pub struct ImportArguments {
+    /// The file to import
+    pub file: PathBuf,
+    /// Table the rows go into, by default named after the file
+    #[arg(long)]
+    pub table: Option<String>,
+    /// Format of the file, by default taken from its extension
+    #[arg(short, long, value_enum)]
+    pub format: Option<ImportFormat>,
 }
@@ -0,0 +1,101 @@
+use std::{
+    fs,
+    panic::{self, PanicHookInfo},
+    path::PathBuf,
+    process,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use ratatui::DefaultTerminal;
+
+/// What the crash report mentions besides the panic itself.
+struct CrashContext {
+    database_path: Option<PathBuf>,
+    last_query: Option<String>,
+}
+
+static CONTEXT: Mutex<CrashContext> = Mutex::new(CrashContext {
+    database_path: None,
+    last_query: None,
+});
+
+/// Restores the terminal when dropped, so an early return or an error leaves
+/// the shell usable too.
+pub struct TerminalGuard;
+impl TerminalGuard {
+    pub fn init() -> (DefaultTerminal, Self) {
+        return (ratatui::init(), Self);
+    }
+}
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        ratatui::restore();
+    }
+}
+
+/// Replaces the panic hook with one that restores the terminal before
+/// anything is printed, writes a crash report to stderr and a log file and
+/// ends the program. A panic on the worker, input or timer thread ends it
+/// too, the UI can't go on without them. Must be installed after
+/// `ratatui::init`, which sets its own hook.
+pub fn install_panic_hook(database_path: PathBuf) {
+    if let Ok(mut context) = CONTEXT.lock() {
+        context.database_path = Some(database_path);
+    }
+    panic::set_hook(Box::new(|info| {
+        ratatui::restore();
+        let report = crash_report(info);
+        eprintln!("{}", report);
+        if let Some(path) = write_crash_log(&report) {
+            eprintln!("The report was saved to {:?}", path);
+        }
+        // the exit code of an unwinding panic
+        process::exit(101);
+    }));
+}
+
+/// Remembers the query about to run for the crash report.
+pub fn record_query(query: &str) {
+    if let Ok(mut context) = CONTEXT.lock() {
+        context.last_query = Some(query.to_string());
+    }
+}
+
+fn crash_report(info: &PanicHookInfo) -> String {
+    let message = match info.payload().downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => match info.payload().downcast_ref::<String>() {
+            Some(message) => message.clone(),
+            None => "unknown panic".to_string(),
+        },
+    };
+    let mut report = format!("sqlite_viewer crashed: {}", message);
+    if let Some(location) = info.location() {
+        report += &format!("\n  at {}:{}", location.file(), location.line());
+    }
+    // the panic may have happened while the lock was held
+    if let Ok(context) = CONTEXT.try_lock() {
+        if let Some(path) = &context.database_path {
+            report += &format!("\nDatabase: {:?}", path);
+        }
+        report += &format!(
+            "\nLast query: {}",
+            context.last_query.as_deref().unwrap_or("(none)")
+        );
+    }
+    return report;
+}
+
+/// Saves the report next to the query history, returns where.
+fn write_crash_log(report: &str) -> Option<PathBuf> {
+    let directory = dirs::data_dir()?.join("sqlite_viewer").join("crashes");
+    fs::create_dir_all(&directory).ok()?;
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let path = directory.join(format!("crash-{}.log", seconds));
+    fs::write(&path, format!("{}\n", report)).ok()?;
+    return Some(path);
+}
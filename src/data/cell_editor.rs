@@ -0,0 +1,800 @@
+use std::collections::HashMap;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use rusqlite::{Connection, params_from_iter, types::Value};
+use sqlparser::{
+    ast::{
+        Expr, GroupByExpr, Ident, ObjectNamePart, Select, SelectItem,
+        SelectItemQualifiedWildcardKind, SetExpr, Statement, TableFactor,
+    },
+    dialect::SQLiteDialect,
+    parser::Parser,
+};
+
+use super::{
+    identifier::quote_identifier,
+    sqlite_database::{SqliteDatabaseState, read_only_error},
+    sqlite_query::{SqliteQueryResult, display_value},
+};
+use crate::error::AppError;
+
+/// Names SQLite accepts for the rowid of a table.
+const ROWID_NAMES: [&str; 3] = ["rowid", "_rowid_", "oid"];
+
+/// Where a result column's values come from.
+#[derive(Clone, PartialEq, Debug)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum ResultColumnSource {
+    COLUMN(String),
+    /// Generated columns are computed by SQLite and can't be written.
+    GENERATED(String),
+    ROWID,
+    /// Expressions, literals and anything else that can't be written back.
+    COMPUTED,
+}
+
+/// How the rows of an editable result are found again in the table.
+#[derive(Clone, Debug)]
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+pub enum RowKey {
+    /// Index of the result column holding the rowid.
+    ROWID(usize),
+    /// Primary key columns and the result columns holding them.
+    PRIMARY_KEY(Vec<(String, usize)>),
+}
+
+/// The table a result was selected from, for results that can be edited.
+#[derive(Clone, Debug)]
+pub struct EditTarget {
+    pub schema: String,
+    pub table: String,
+    pub key: RowKey,
+    /// One entry per result column.
+    pub sources: Vec<ResultColumnSource>,
+}
+impl EditTarget {
+    /// Works out whether `result` came straight from one table, i.e. is a
+    /// plain `SELECT` without joins, grouping or DISTINCT, and how its rows
+    /// can be told apart.
+    pub fn for_result(
+        connection: &Connection,
+        result: &SqliteQueryResult,
+    ) -> Result<Self, AppError> {
+        let (select, schema, table) = single_table_select(connection, &result.sql)?;
+        let not_single_table = || {
+            return AppError::SCHEMA(
+                "only results of a SELECT from a single table can be edited".to_string(),
+            );
+        };
+        let alias = match &select.from[0].relation {
+            TableFactor::Table { alias, .. } => alias.clone(),
+            _ => None,
+        };
+
+        // `SqliteTable` needs a connection it can keep, columns are read here instead.
+        // `table_xinfo` includes generated columns, hidden 2 and 3; the hidden
+        // columns of virtual tables (1) aren't part of `*`.
+        let table_columns: Vec<(String, usize, bool)> = connection
+            .prepare("SELECT name, pk, hidden IN (2, 3) FROM pragma_table_xinfo(?1, ?2) WHERE hidden != 1")?
+            .query_map([&table, &schema], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?
+            .collect::<Result<_, _>>()?;
+        let column_source = |(name, _, generated): &(String, usize, bool)| {
+            if *generated {
+                return ResultColumnSource::GENERATED(name.clone());
+            }
+            return ResultColumnSource::COLUMN(name.clone());
+        };
+        let find_column = |name: &str| {
+            return table_columns
+                .iter()
+                .find(|(column, _, _)| column.eq_ignore_ascii_case(name))
+                .map(column_source);
+        };
+        let qualifier_matches = |qualifier: &str| {
+            return qualifier.eq_ignore_ascii_case(&table)
+                || alias
+                    .as_ref()
+                    .is_some_and(|a| a.name.value.eq_ignore_ascii_case(qualifier));
+        };
+        let identifier_source = |name: &str| {
+            if let Some(source) = find_column(name) {
+                return source;
+            }
+            if ROWID_NAMES.iter().any(|r| r.eq_ignore_ascii_case(name)) {
+                return ResultColumnSource::ROWID;
+            }
+            return ResultColumnSource::COMPUTED;
+        };
+        let expression_source = |expr: &Expr| {
+            return match expr {
+                Expr::Identifier(ident) => identifier_source(&ident.value),
+                Expr::CompoundIdentifier(idents) => match idents.as_slice() {
+                    [qualifier, column] if qualifier_matches(&qualifier.value) => {
+                        identifier_source(&column.value)
+                    }
+                    _ => ResultColumnSource::COMPUTED,
+                },
+                _ => ResultColumnSource::COMPUTED,
+            };
+        };
+
+        let mut sources = Vec::new();
+        for item in &select.projection {
+            match item {
+                SelectItem::Wildcard(_) => sources.extend(table_columns.iter().map(column_source)),
+                SelectItem::QualifiedWildcard(
+                    SelectItemQualifiedWildcardKind::ObjectName(n),
+                    _,
+                ) if matches!(n.0.as_slice(), [ObjectNamePart::Identifier(q)] if qualifier_matches(&q.value)) => {
+                    sources.extend(table_columns.iter().map(column_source))
+                }
+                SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => {
+                    sources.push(expression_source(expr))
+                }
+                _ => sources.push(ResultColumnSource::COMPUTED),
+            }
+        }
+        if sources.len() != result.columns.len() {
+            return Err(not_single_table());
+        }
+
+        if !sources
+            .iter()
+            .any(|s| matches!(s, ResultColumnSource::COLUMN(_)))
+        {
+            return Err(AppError::SCHEMA(format!(
+                "the result has no columns of {}, nothing can be edited",
+                table
+            )));
+        }
+
+        let mut primary_key: Vec<&(String, usize, bool)> =
+            table_columns.iter().filter(|(_, pk, _)| *pk > 0).collect();
+        primary_key.sort_by_key(|(_, pk, _)| *pk);
+        let key_columns: Option<Vec<(String, usize)>> = primary_key
+            .iter()
+            .map(|column| {
+                let name = &column.0;
+                let index = sources.iter().position(|s| *s == column_source(column))?;
+                return Some((name.clone(), index));
+            })
+            .collect();
+        let key = match key_columns {
+            Some(columns) if !columns.is_empty() => RowKey::PRIMARY_KEY(columns),
+            _ => match sources.iter().position(|s| *s == ResultColumnSource::ROWID) {
+                Some(index) => RowKey::ROWID(index),
+                None => {
+                    return Err(AppError::SCHEMA(format!(
+                        "rows of {} can't be told apart, select its rowid or primary key too",
+                        table
+                    )));
+                }
+            },
+        };
+        return Ok(Self {
+            schema,
+            table,
+            key,
+            sources,
+        });
+    }
+
+    pub(crate) fn key_values(&self, row: &[Value]) -> Vec<Value> {
+        return match &self.key {
+            RowKey::ROWID(index) => vec![row[*index].clone()],
+            RowKey::PRIMARY_KEY(columns) => columns
+                .iter()
+                .map(|(_, index)| row[*index].clone())
+                .collect(),
+        };
+    }
+
+    /// Where the result column `column` is in the key, when it is part of it.
+    fn key_position(&self, column: usize) -> Option<usize> {
+        return match &self.key {
+            RowKey::ROWID(_) => None,
+            RowKey::PRIMARY_KEY(columns) => columns.iter().position(|(_, index)| *index == column),
+        };
+    }
+
+    /// Names of the key columns, `rowid` when rows are found by rowid.
+    pub fn key_names(&self) -> Vec<String> {
+        return match &self.key {
+            RowKey::ROWID(_) => vec!["rowid".to_string()],
+            RowKey::PRIMARY_KEY(columns) => columns.iter().map(|(name, _)| name.clone()).collect(),
+        };
+    }
+
+    /// `WHERE` condition matching one row, the key values are bound starting
+    /// at parameter `first_parameter`.
+    fn key_condition(&self, first_parameter: usize) -> String {
+        return self
+            .key_names()
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let name = match &self.key {
+                    RowKey::ROWID(_) => name.clone(),
+                    RowKey::PRIMARY_KEY(_) => quote_identifier(name),
+                };
+                return format!("{} IS ?{}", name, first_parameter + i);
+            })
+            .collect::<Vec<String>>()
+            .join(" AND ");
+    }
+
+    /// `UPDATE` for one column of one row, the new value is `?1` followed by
+    /// the key values.
+    pub fn update_sql(&self, column: &str) -> String {
+        return format!(
+            "UPDATE {}.{} SET {} = ?1 WHERE {}",
+            quote_identifier(&self.schema),
+            quote_identifier(&self.table),
+            quote_identifier(column),
+            self.key_condition(2)
+        );
+    }
+
+    /// `DELETE` for one row, bound to the key values.
+    pub fn delete_sql(&self) -> String {
+        return format!(
+            "DELETE FROM {}.{} WHERE {}",
+            quote_identifier(&self.schema),
+            quote_identifier(&self.table),
+            self.key_condition(1)
+        );
+    }
+}
+
+/// A changed cell waiting for the edits to be committed.
+pub struct PendingEdit {
+    /// Position in the result.
+    pub row: usize,
+    pub column: usize,
+    pub value: Value,
+    /// Values identifying the row as it is stored. Committing moves them
+    /// along when an edit before this one changed the row's key.
+    pub key: Vec<Value>,
+}
+
+/// The cell currently being typed into.
+pub struct CellEditor {
+    pub row: usize,
+    pub column: usize,
+    pub text: String,
+    /// Set with Ctrl+N, written as NULL instead of the text.
+    pub null: bool,
+    /// Value the cell had when the editor opened.
+    original: Value,
+}
+
+/// Inline editing of result cells.
+impl SqliteDatabaseState {
+    /// Opens the editor on the selected cell.
+    pub fn start_cell_edit(&mut self) {
+        self.edit_error = None;
+        if self.database.readonly {
+            self.edit_error = Some(read_only_error());
+            return;
+        }
+        let Some(result) = &self.queried_table_state else {
+            return;
+        };
+        if result.row_count() == 0 || !self.ensure_edit_target() {
+            return;
+        }
+        let Some(result) = &self.queried_table_state else {
+            return;
+        };
+        let (row, column) = self.result_cursor;
+        let Some(target) = &self.edit_target else {
+            return;
+        };
+        if let ResultColumnSource::GENERATED(name) = &target.sources[column] {
+            self.edit_error = Some(AppError::SCHEMA(format!(
+                "{} is a generated column of {}, it can't be edited",
+                name, target.table
+            )));
+            return;
+        }
+        if !matches!(target.sources[column], ResultColumnSource::COLUMN(_)) {
+            self.edit_error = Some(AppError::SCHEMA(format!(
+                "{} is not a column of {}, it can't be edited",
+                result.column_names()[column],
+                target.table
+            )));
+            return;
+        }
+        let current = match self.pending_edit(row, column) {
+            Some(edit) => edit.value.clone(),
+            None => result
+                .window(row, 1)
+                .first()
+                .map(|r| r[column].clone())
+                .unwrap_or(Value::Null),
+        };
+        if let Value::Blob(_) = current {
+            self.edit_error = Some(AppError::UI("blobs can't be edited inline".to_string()));
+            return;
+        }
+        self.cell_editor = Some(CellEditor {
+            row,
+            column,
+            text: match &current {
+                Value::Null => String::new(),
+                value => display_value(value),
+            },
+            null: matches!(current, Value::Null),
+            original: current,
+        });
+    }
+
+    /// Works out the edit target of the current result once, returns false
+    /// with `edit_error` set when the result can't be changed.
+    pub(crate) fn ensure_edit_target(&mut self) -> bool {
+        if self.edit_target.is_some() {
+            return true;
+        }
+        let Some(result) = &self.queried_table_state else {
+            return false;
+        };
+        match EditTarget::for_result(&self.database.connection, result) {
+            Ok(target) => {
+                self.edit_target = Some(target);
+                return true;
+            }
+            Err(e) => {
+                self.edit_error = Some(e);
+                return false;
+            }
+        }
+    }
+
+    pub fn pending_edit(&self, row: usize, column: usize) -> Option<&PendingEdit> {
+        return self
+            .pending_edits
+            .iter()
+            .find(|e| e.row == row && e.column == column);
+    }
+
+    /// Turns the editor's text into a pending edit. Text that still parses as
+    /// the cell's old numeric type keeps that type, anything else is bound as
+    /// text and left to the column's affinity.
+    fn confirm_cell_edit(&mut self) {
+        let Some(editor) = self.cell_editor.take() else {
+            return;
+        };
+        let (Some(result), Some(target)) = (&self.queried_table_state, &self.edit_target) else {
+            return;
+        };
+        let Some(row) = result.window(editor.row, 1).first() else {
+            return;
+        };
+        let value = match (&editor.original, editor.null) {
+            (_, true) => Value::Null,
+            (Value::Integer(_), _) if let Ok(i) = editor.text.parse() => Value::Integer(i),
+            (Value::Real(_), _) if let Ok(f) = editor.text.parse() => Value::Real(f),
+            _ => Value::Text(editor.text),
+        };
+        if value == editor.original {
+            return;
+        }
+        let key = target.key_values(row);
+        let stored = row.get(editor.column).unwrap_or(&Value::Null).clone();
+        self.pending_edits
+            .retain(|e| !(e.row == editor.row && e.column == editor.column));
+        // changing a cell back to what is stored needs no update
+        if value != stored {
+            self.pending_edits.push(PendingEdit {
+                row: editor.row,
+                column: editor.column,
+                value,
+                key,
+            });
+        }
+    }
+
+    pub fn discard_pending_edits(&mut self) {
+        self.pending_edits.clear();
+        self.cell_editor = None;
+        self.edit_error = None;
+    }
+
+    /// Writes every pending edit inside one savepoint, which also works when
+    /// a transaction is already open. Nothing is written if any update fails.
+    pub fn commit_pending_edits(&mut self) {
+        let Some(target) = &self.edit_target else {
+            return;
+        };
+        if self.pending_edits.is_empty() {
+            return;
+        }
+        let connection = &self.database.connection;
+        let apply = || -> Result<(), AppError> {
+            // the key each row has by now, after the edits of its key columns
+            let mut keys: HashMap<usize, Vec<Value>> = HashMap::new();
+            for edit in &self.pending_edits {
+                let ResultColumnSource::COLUMN(column) = &target.sources[edit.column] else {
+                    continue;
+                };
+                let key = keys.entry(edit.row).or_insert_with(|| edit.key.clone());
+                let parameters = std::iter::once(&edit.value).chain(key.iter());
+                let changed =
+                    connection.execute(&target.update_sql(column), params_from_iter(parameters))?;
+                if let Some(position) = target.key_position(edit.column) {
+                    key[position] = edit.value.clone();
+                }
+                if changed == 0 {
+                    return Err(AppError::SCHEMA(format!(
+                        "row {} of the result no longer exists in {}",
+                        edit.row + 1,
+                        target.table
+                    )));
+                }
+            }
+            return Ok(());
+        };
+        let outcome = connection
+            .execute_batch("SAVEPOINT pending_edits")
+            .map_err(AppError::from)
+            .and_then(|_| apply());
+        match outcome {
+            Ok(()) => {
+                if let Err(e) = connection.execute_batch("RELEASE pending_edits") {
+                    self.edit_error = Some(e.into());
+                    return;
+                }
+                self.pending_edits.clear();
+                self.edit_error = None;
+                self.refresh_result();
+            }
+            Err(e) => {
+                let _ =
+                    connection.execute_batch("ROLLBACK TO pending_edits; RELEASE pending_edits");
+                self.edit_error = Some(e);
+            }
+        }
+    }
+
+    /// Runs the query behind the result again, keeping the selected cell.
+    pub(crate) fn refresh_result(&mut self) {
+        let Some(result) = &self.queried_table_state else {
+            return;
+        };
+        match SqliteQueryResult::query(
+            &self.database.connection,
+            &result.sql,
+            result.columns.clone(),
+            result.params.clone(),
+        ) {
+            Ok(mut refreshed) => {
+                refreshed.hidden_columns = result.hidden_columns;
+                self.queried_table_state = Some(refreshed);
+            }
+            Err(e) => {
+                self.edit_error = Some(e.into());
+                return;
+            }
+        }
+        let (row, column) = self.result_cursor;
+        self.move_result_cursor_to(Some(row), Some(column));
+    }
+
+    /// Keys of the result grid that deal with editing, returns false for the
+    /// ones it leaves to navigation.
+    pub(crate) fn read_cell_edit_keys(&mut self, event: &KeyEvent) -> bool {
+        let control = event.modifiers.contains(KeyModifiers::CONTROL);
+        if let Some(editor) = &mut self.cell_editor {
+            match event.code {
+                KeyCode::Esc => self.cell_editor = None,
+                KeyCode::Enter => self.confirm_cell_edit(),
+                KeyCode::Char('n') if control => {
+                    editor.null = true;
+                    editor.text.clear();
+                }
+                KeyCode::Backspace => {
+                    editor.null = false;
+                    editor.text.pop();
+                }
+                KeyCode::Char(c)
+                    if !event
+                        .modifiers
+                        .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+                {
+                    editor.null = false;
+                    editor.text.push(c);
+                }
+                _ => (),
+            }
+            return true;
+        }
+        match event.code {
+            KeyCode::Enter | KeyCode::F(2) => self.start_cell_edit(),
+            KeyCode::Char('s') if control => self.commit_pending_edits(),
+            KeyCode::Char('z') if control => self.discard_pending_edits(),
+            _ => return false,
+        }
+        return true;
+    }
+}
+
+/// For a plain `SELECT *` from a table without a primary key, `sql` with the
+/// rowid added as the last column so the rows can be edited. The result
+/// keeps that column hidden.
+pub fn with_rowid_key(connection: &Connection, sql: &str) -> Option<String> {
+    let (select, schema, table) = single_table_select(connection, sql).ok()?;
+    if !matches!(select.projection.as_slice(), [SelectItem::Wildcard(_)]) {
+        return None;
+    }
+    let columns: Vec<(String, usize)> = connection
+        .prepare("SELECT name, pk FROM pragma_table_xinfo(?1, ?2)")
+        .ok()?
+        .query_map([&table, &schema], |row| Ok((row.get(0)?, row.get(1)?)))
+        .ok()?
+        .collect::<Result<_, _>>()
+        .ok()?;
+    if columns.iter().any(|(_, pk)| *pk > 0) {
+        return None;
+    }
+    // a column can take the name, the other names still reach the rowid
+    let rowid = ROWID_NAMES
+        .iter()
+        .find(|r| !columns.iter().any(|(name, _)| name.eq_ignore_ascii_case(r)))?;
+    let mut statements = Parser::parse_sql(&SQLiteDialect {}, sql).ok()?;
+    let Some(Statement::Query(query)) = statements.first_mut() else {
+        return None;
+    };
+    let SetExpr::Select(select) = query.body.as_mut() else {
+        return None;
+    };
+    select
+        .projection
+        .push(SelectItem::UnnamedExpr(Expr::Identifier(Ident::new(
+            *rowid,
+        ))));
+    let keyed = statements[0].to_string();
+    // virtual tables may have no rowid
+    connection.prepare(&keyed).ok()?;
+    return Some(keyed);
+}
+
+/// Parses `sql` as a plain `SELECT` from one table, i.e. without joins,
+/// grouping, DISTINCT or a WITH clause, and returns it with the schema and
+/// name of that table.
+pub fn single_table_select(
+    connection: &Connection,
+    sql: &str,
+) -> Result<(Select, String, String), AppError> {
+    let not_single_table = || {
+        return AppError::SCHEMA(
+            "only results of a SELECT from a single table can be changed".to_string(),
+        );
+    };
+    let statements = Parser::parse_sql(&SQLiteDialect {}, sql).map_err(|_| not_single_table())?;
+    let [Statement::Query(query)] = statements.as_slice() else {
+        return Err(not_single_table());
+    };
+    let SetExpr::Select(select) = query.body.as_ref() else {
+        return Err(not_single_table());
+    };
+    let grouped = match &select.group_by {
+        GroupByExpr::All(_) => true,
+        GroupByExpr::Expressions(expressions, _) => !expressions.is_empty(),
+    };
+    if query.with.is_some()
+        || select.distinct.is_some()
+        || grouped
+        || select.having.is_some()
+        || select.from.len() != 1
+        || !select.from[0].joins.is_empty()
+    {
+        return Err(not_single_table());
+    }
+    let TableFactor::Table { name, args, .. } = &select.from[0].relation else {
+        return Err(not_single_table());
+    };
+    if args.is_some() {
+        return Err(not_single_table());
+    }
+    let parts: Vec<String> = name
+        .0
+        .iter()
+        .filter_map(|part| match part {
+            ObjectNamePart::Identifier(ident) => Some(ident.value.clone()),
+            _ => None,
+        })
+        .collect();
+    let (schema, table) = match parts.as_slice() {
+        [table] => ("main".to_string(), table.clone()),
+        [schema, table] => (schema.clone(), table.clone()),
+        _ => return Err(not_single_table()),
+    };
+
+    let kind: Option<String> = connection
+        .query_row(
+            &format!(
+                "SELECT type FROM {}.sqlite_master WHERE name = ?1 COLLATE NOCASE",
+                quote_identifier(&schema)
+            ),
+            [&table],
+            |row| row.get(0),
+        )
+        .ok();
+    if kind.as_deref() != Some("table") {
+        return Err(AppError::SCHEMA(format!(
+            "{} is not a table, it can't be changed",
+            table
+        )));
+    }
+    return Ok((select.as_ref().clone(), schema, table));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::data::{query_worker::run_statement, sqlite_database::SqliteDatabase};
+
+    fn connection() -> Connection {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE plain (a, b);
+                 CREATE TABLE keyed (x, id INTEGER, y, PRIMARY KEY (y, id));
+                 CREATE VIEW v AS SELECT * FROM plain;",
+            )
+            .unwrap();
+        return connection;
+    }
+
+    fn target(connection: &Connection, sql: &str) -> Result<EditTarget, AppError> {
+        let columns = connection
+            .prepare(sql)?
+            .column_names()
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        let result = SqliteQueryResult::query(connection, sql, columns, Vec::new())?;
+        return EditTarget::for_result(connection, &result);
+    }
+
+    fn column(name: &str) -> ResultColumnSource {
+        return ResultColumnSource::COLUMN(name.to_string());
+    }
+
+    #[test]
+    fn columns_are_traced_back_to_the_table() {
+        let connection = connection();
+        let target = target(
+            &connection,
+            "SELECT p.B, rowid, a || 'x', p.* FROM plain AS p",
+        )
+        .unwrap();
+        assert_eq!(target.schema, "main");
+        assert_eq!(target.table, "plain");
+        assert_eq!(
+            target.sources,
+            vec![
+                column("b"),
+                ResultColumnSource::ROWID,
+                ResultColumnSource::COMPUTED,
+                column("a"),
+                column("b")
+            ]
+        );
+        assert!(matches!(target.key, RowKey::ROWID(1)));
+        assert_eq!(
+            target.update_sql("a"),
+            "UPDATE \"main\".\"plain\" SET \"a\" = ?1 WHERE rowid IS ?2"
+        );
+    }
+
+    #[test]
+    fn primary_key_columns_are_used_in_key_order() {
+        let connection = connection();
+        let target = target(&connection, "SELECT * FROM main.keyed").unwrap();
+        let RowKey::PRIMARY_KEY(columns) = &target.key else {
+            panic!("expected a primary key");
+        };
+        assert_eq!(columns, &vec![("y".to_string(), 2), ("id".to_string(), 1)]);
+        assert_eq!(
+            target.update_sql("x"),
+            "UPDATE \"main\".\"keyed\" SET \"x\" = ?1 WHERE \"y\" IS ?2 AND \"id\" IS ?3"
+        );
+    }
+
+    #[test]
+    fn generated_columns_are_kept_apart() {
+        let connection = connection();
+        connection
+            .execute_batch("CREATE TABLE g (a, b AS (a * 2), c AS (a + 1) STORED)")
+            .unwrap();
+        let target = target(&connection, "SELECT rowid, * FROM g").unwrap();
+        assert_eq!(
+            target.sources,
+            vec![
+                ResultColumnSource::ROWID,
+                column("a"),
+                ResultColumnSource::GENERATED("b".to_string()),
+                ResultColumnSource::GENERATED("c".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn results_that_cant_be_written_back_are_refused() {
+        let connection = connection();
+        for sql in [
+            "SELECT a FROM plain",
+            "SELECT x, y FROM keyed",
+            "SELECT DISTINCT rowid, a FROM plain",
+            "SELECT rowid, count(*) FROM plain GROUP BY a",
+            "SELECT p.rowid, p.a FROM plain p JOIN keyed",
+            "SELECT rowid, 1 FROM plain",
+            "SELECT * FROM v",
+            "SELECT 1",
+        ] {
+            assert!(target(&connection, sql).is_err(), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn tables_without_a_primary_key_get_a_hidden_rowid() {
+        let connection = connection();
+        connection
+            .execute_batch("CREATE TABLE shadowed (rowid, v)")
+            .unwrap();
+        let result = run_statement(&connection, "SELECT * FROM plain", &[], false).unwrap();
+        assert_eq!(result.column_names(), ["a", "b"]);
+        assert_eq!(result.hidden_columns, 1);
+        let target = EditTarget::for_result(&connection, &result).unwrap();
+        assert!(matches!(target.key, RowKey::ROWID(2)));
+
+        let keyed = with_rowid_key(&connection, "SELECT * FROM shadowed").unwrap();
+        assert!(keyed.contains("_rowid_"), "{}", keyed);
+        for sql in [
+            "SELECT * FROM keyed",
+            "SELECT a FROM plain",
+            "SELECT * FROM v",
+        ] {
+            assert_eq!(with_rowid_key(&connection, sql), None, "{}", sql);
+        }
+    }
+
+    #[test]
+    fn edits_after_a_key_change_still_find_the_row() {
+        let database = SqliteDatabase::open(PathBuf::from(":memory:"), false, true).unwrap();
+        let mut state = SqliteDatabaseState::new(&database);
+        state
+            .database
+            .connection
+            .execute_batch(
+                "CREATE TABLE k (id INTEGER PRIMARY KEY, name); INSERT INTO k VALUES (1, 'a');",
+            )
+            .unwrap();
+        state.current_query = "SELECT * FROM k".to_string();
+        state.execute();
+        assert!(state.ensure_edit_target());
+        for (column, value) in [(0, Value::Integer(5)), (1, Value::Text("b".to_string()))] {
+            state.pending_edits.push(PendingEdit {
+                row: 0,
+                column,
+                value,
+                key: vec![Value::Integer(1)],
+            });
+        }
+        state.commit_pending_edits();
+        assert!(state.edit_error.is_none());
+        let row: (i64, String) = state
+            .database
+            .connection
+            .query_row("SELECT id, name FROM k", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(row, (5, "b".to_string()));
+    }
+}
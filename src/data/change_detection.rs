@@ -0,0 +1,70 @@
+use std::time::Instant;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use rusqlite::{Connection, Error};
+
+use super::sqlite_database::{SqliteDatabaseState, pending_edits_error};
+
+/// Changes whenever another connection, in this process or another one,
+/// commits to the database. Commits on `connection` itself don't count.
+pub fn data_version(connection: &Connection) -> Result<i64, Error> {
+    return connection.query_row("PRAGMA data_version", [], |row| row.get(0));
+}
+
+/// Noticing commits made outside of the UI's connection.
+impl SqliteDatabaseState {
+    /// Looks for external changes and reloads the object tree when there
+    /// are any. Does nothing while the database is unchanged, and while the
+    /// worker runs a query: its commits would look external until
+    /// `acknowledge_own_changes` runs.
+    pub fn sync(&mut self) {
+        if self.running_query.is_some() {
+            return;
+        }
+        let version = match data_version(&self.database.connection) {
+            Ok(version) => version,
+            Err(e) => {
+                self.error = Some(e.into());
+                return;
+            }
+        };
+        if version == self.data_version {
+            return;
+        }
+        self.data_version = version;
+        self.external_change = Some(Instant::now());
+        self.reload_schema();
+    }
+
+    /// The query worker has its own connection, so its commits look external
+    /// to the UI's connection. Called after a query that writes, which may
+    /// also have changed the schema.
+    pub(crate) fn acknowledge_own_changes(&mut self) {
+        self.reload_schema();
+        if let Ok(version) = data_version(&self.database.connection) {
+            self.data_version = version;
+        }
+    }
+
+    /// Runs the query behind the current result again after an external
+    /// change, keeping the selected cell.
+    pub fn refresh_after_change(&mut self) {
+        if !self.pending_edits.is_empty() {
+            self.edit_error = Some(pending_edits_error());
+            return;
+        }
+        self.external_change = None;
+        self.edit_error = None;
+        self.refresh_result();
+    }
+
+    /// F8 refreshes the result once a change was noticed, returns false for
+    /// other keys.
+    pub(crate) fn read_change_keys(&mut self, event: &KeyEvent) -> bool {
+        if self.external_change.is_none() || event.code != KeyCode::F(8) {
+            return false;
+        }
+        self.refresh_after_change();
+        return true;
+    }
+}
@@ -0,0 +1,405 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use sqlparser::{
+    dialect::SQLiteDialect,
+    keywords::{ALL_KEYWORDS, Keyword},
+    tokenizer::{Token, Tokenizer},
+};
+
+use super::{identifier::quote_identifier_if_needed, sqlite_database::SqliteDatabaseState};
+
+/// Suggestions shown in the popup at once.
+pub const MAX_COMPLETIONS: usize = 10;
+/// Fuzzy matches scoring below this are left out.
+const MIN_SIMILARITY: f64 = 0.8;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum CompletionKind {
+    KEYWORD,
+    TABLE,
+    COLUMN,
+}
+impl CompletionKind {
+    pub fn label(&self) -> &'static str {
+        return match self {
+            CompletionKind::KEYWORD => "keyword",
+            CompletionKind::TABLE => "table",
+            CompletionKind::COLUMN => "column",
+        };
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Completion {
+    pub text: String,
+    pub kind: CompletionKind,
+}
+
+pub struct CompletionPopup {
+    pub items: Vec<Completion>,
+    pub selected: usize,
+    /// Characters before the cursor replaced when a suggestion is accepted.
+    pub prefix_length: usize,
+}
+
+impl SqliteDatabaseState {
+    /// Word characters directly before the cursor.
+    fn completion_prefix(&self) -> String {
+        let (column, row) = self.current_query_cursor;
+        let line = self.query_lines()[row];
+        let before: Vec<char> = line.chars().take(column).collect();
+        let start = before
+            .iter()
+            .rposition(|c| !(c.is_alphanumeric() || *c == '_'))
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        return before[start..].iter().collect();
+    }
+
+    /// Recomputes the popup for the word at the cursor. With `force` the popup
+    /// also opens for an empty word (Ctrl+Space).
+    pub fn update_completion(&mut self, force: bool) {
+        let prefix = self.completion_prefix();
+        if prefix.is_empty() && !force {
+            self.completion = None;
+            return;
+        }
+        let items = self.completions(&prefix);
+        if items.is_empty() {
+            self.completion = None;
+            return;
+        }
+        self.completion = Some(CompletionPopup {
+            items,
+            selected: 0,
+            prefix_length: prefix.chars().count(),
+        });
+    }
+
+    pub fn accept_completion(&mut self) {
+        let Some(popup) = self.completion.take() else {
+            return;
+        };
+        let Some(completion) = popup.items.get(popup.selected) else {
+            return;
+        };
+        for _ in 0..popup.prefix_length {
+            self.delete_backward();
+        }
+        let text = match completion.kind {
+            CompletionKind::TABLE | CompletionKind::COLUMN => {
+                quote_identifier_if_needed(&completion.text)
+            }
+            _ => completion.text.clone(),
+        };
+        self.insert_str(&text);
+    }
+
+    /// Handles keys while the popup is open, returns false for keys it
+    /// leaves to the editor.
+    pub(crate) fn read_completion_keys(&mut self, event: &KeyEvent) -> bool {
+        let Some(popup) = &mut self.completion else {
+            return false;
+        };
+        match event.code {
+            KeyCode::Tab | KeyCode::Enter => self.accept_completion(),
+            KeyCode::Esc => self.completion = None,
+            KeyCode::Up => popup.selected = popup.selected.saturating_sub(1),
+            KeyCode::Down => popup.selected = (popup.selected + 1).min(popup.items.len() - 1),
+            _ => return false,
+        }
+        return true;
+    }
+
+    /// Ranked suggestions for `prefix` at the cursor. After FROM/JOIN-like
+    /// keywords only tables are suggested, after `name.` only the columns of
+    /// that table, otherwise columns of the tables used in the query, tables
+    /// and keywords.
+    fn completions(&self, prefix: &str) -> Vec<Completion> {
+        let query = &self.current_query;
+        let tokens = Tokenizer::new(&SQLiteDialect {}, query)
+            .tokenize()
+            .unwrap_or_default();
+        let cursor_offset: usize = self.query_lines()[..self.current_query_cursor.1]
+            .iter()
+            .map(|l| l.chars().count() + 1)
+            .sum::<usize>()
+            + self.current_query_cursor.0;
+        let word_start = cursor_offset - prefix.chars().count();
+        let previous = match word_start {
+            0 => None,
+            _ => get_last_non_whitespace_token(&tokens, word_start - 1),
+        };
+
+        let referenced = referenced_tables(&tokens);
+        let mut candidates: Vec<Completion> = Vec::new();
+        let column_candidates = |tables: &[String]| -> Vec<Completion> {
+            return self
+                .tables
+                .iter()
+                .filter(|t| tables.iter().any(|name| name.eq_ignore_ascii_case(&t.name)))
+                .flat_map(|t| t.columns().unwrap_or_default().into_keys())
+                .map(|name| Completion {
+                    text: name,
+                    kind: CompletionKind::COLUMN,
+                })
+                .collect();
+        };
+        let table_candidates = || -> Vec<Completion> {
+            return self
+                .tables
+                .iter()
+                .map(|t| Completion {
+                    text: t.name.clone(),
+                    kind: CompletionKind::TABLE,
+                })
+                .collect();
+        };
+
+        match previous {
+            Some((_, Token::Word(w)))
+                if matches!(
+                    w.keyword,
+                    Keyword::FROM
+                        | Keyword::JOIN
+                        | Keyword::INTO
+                        | Keyword::UPDATE
+                        | Keyword::TABLE
+                ) =>
+            {
+                candidates.extend(table_candidates());
+            }
+            Some((index, Token::Period)) => {
+                // `alias.` or `table.`
+                let qualifier = tokens[..index - 1].iter().rev().find_map(|t| match t {
+                    Token::Word(w) => Some(w.value.clone()),
+                    Token::Whitespace(_) => None,
+                    _ => Some(String::new()),
+                });
+                if let Some(qualifier) = qualifier {
+                    let table = referenced
+                        .iter()
+                        .find(|(_, alias)| {
+                            alias
+                                .as_ref()
+                                .is_some_and(|a| a.eq_ignore_ascii_case(&qualifier))
+                        })
+                        .map(|(name, _)| name.clone())
+                        .unwrap_or(qualifier);
+                    candidates.extend(column_candidates(&[table]));
+                }
+            }
+            _ => {
+                let names: Vec<String> = referenced.into_iter().map(|(name, _)| name).collect();
+                candidates.extend(column_candidates(&names));
+                candidates.extend(table_candidates());
+                candidates.extend(ALL_KEYWORDS.iter().map(|k| Completion {
+                    text: k.to_string(),
+                    kind: CompletionKind::KEYWORD,
+                }));
+            }
+        }
+        return rank(candidates, prefix);
+    }
+}
+
+/// Sorts candidates by how well they match `prefix`: prefix matches first,
+/// then by Jaro-Winkler similarity. Columns and tables win ties over keywords.
+fn rank(candidates: Vec<Completion>, prefix: &str) -> Vec<Completion> {
+    let prefix = prefix.to_lowercase();
+    let mut scored: Vec<(f64, Completion)> = candidates
+        .into_iter()
+        .filter(|c| !c.text.eq_ignore_ascii_case(&prefix))
+        .filter_map(|c| {
+            let text = c.text.to_lowercase();
+            let score = if prefix.is_empty() {
+                1.0
+            } else if text.starts_with(&prefix) {
+                2.0 + strsim::jaro_winkler(&prefix, &text)
+            } else {
+                strsim::jaro_winkler(&prefix, &text)
+            };
+            if score < MIN_SIMILARITY {
+                return None;
+            }
+            let kind_bonus = match c.kind {
+                CompletionKind::COLUMN => 0.02,
+                CompletionKind::TABLE => 0.01,
+                CompletionKind::KEYWORD => 0.0,
+            };
+            return Some((score + kind_bonus, c));
+        })
+        .collect();
+    scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    let mut seen = Vec::new();
+    return scored
+        .into_iter()
+        .map(|(_, c)| c)
+        .filter(|c| {
+            if seen.contains(&c.text) {
+                return false;
+            }
+            seen.push(c.text.clone());
+            return true;
+        })
+        .take(MAX_COMPLETIONS)
+        .collect();
+}
+
+/// Tables named after FROM/JOIN with their alias, e.g. `FROM a x, b JOIN c AS y`.
+fn referenced_tables(tokens: &[Token]) -> Vec<(String, Option<String>)> {
+    let words: Vec<&Token> = tokens
+        .iter()
+        .filter(|t| !matches!(t, Token::Whitespace(_)))
+        .collect();
+    let mut tables = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        let Token::Word(w) = words[i] else {
+            i += 1;
+            continue;
+        };
+        if !matches!(w.keyword, Keyword::FROM | Keyword::JOIN) {
+            i += 1;
+            continue;
+        }
+        i += 1;
+        // table list, separated by commas after FROM
+        while let Some(Token::Word(table)) = words.get(i) {
+            if table.keyword != Keyword::NoKeyword && table.quote_style.is_none() {
+                break;
+            }
+            i += 1;
+            if let Some(Token::Word(w)) = words.get(i)
+                && w.keyword == Keyword::AS
+            {
+                i += 1;
+            }
+            let mut alias = None;
+            if let Some(Token::Word(w)) = words.get(i)
+                && (w.keyword == Keyword::NoKeyword || w.quote_style.is_some())
+            {
+                alias = Some(w.value.clone());
+                i += 1;
+            }
+            tables.push((table.value.clone(), alias));
+            if let Some(Token::Comma) = words.get(i) {
+                i += 1;
+            } else {
+                break;
+            }
+        }
+    }
+    return tables;
+}
+
+/// Index (one past the token) and the token of the last non-whitespace token
+/// starting at or before character `column` of the tokenized text.
+pub fn get_last_non_whitespace_token(tokens: &[Token], column: usize) -> Option<(usize, &Token)> {
+    if tokens.is_empty() {
+        return None;
+    }
+    let mut i = 0;
+    let mut last_non_whitespace = None;
+    let mut current_column = 0;
+    while current_column <= column && i < tokens.len() {
+        let token = &tokens[i];
+
+        if let Token::Whitespace(_) = token {
+            i += 1;
+            current_column += token.to_string().chars().count();
+            continue;
+        }
+        i += 1;
+        current_column += token.to_string().chars().count();
+        last_non_whitespace = Some((i, token));
+    }
+    return last_non_whitespace;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::super::sqlite_database::SqliteDatabase;
+    use super::*;
+
+    fn tables_in(query: &str) -> Vec<(String, Option<String>)> {
+        let tokens = Tokenizer::new(&SQLiteDialect {}, query).tokenize().unwrap();
+        return referenced_tables(&tokens);
+    }
+
+    fn table(name: &str, alias: Option<&str>) -> (String, Option<String>) {
+        return (name.to_string(), alias.map(|a| a.to_string()));
+    }
+
+    #[test]
+    fn referenced_tables_come_with_their_aliases() {
+        assert_eq!(
+            tables_in("SELECT * FROM a x, b JOIN c AS y WHERE x.id = y.id"),
+            vec![
+                table("a", Some("x")),
+                table("b", None),
+                table("c", Some("y"))
+            ]
+        );
+        assert_eq!(
+            tables_in("SELECT * FROM \"my table\" \"t\" LEFT JOIN d ON 1"),
+            vec![table("my table", Some("t")), table("d", None)]
+        );
+    }
+
+    #[test]
+    fn keywords_after_a_table_are_not_aliases() {
+        assert_eq!(
+            tables_in("SELECT * FROM a WHERE 1 ORDER BY 1"),
+            vec![table("a", None)]
+        );
+        assert_eq!(tables_in("SELECT * FROM (SELECT 1)"), vec![]);
+        assert_eq!(tables_in("SELECT 1"), vec![]);
+    }
+
+    #[test]
+    fn prefix_matches_rank_first_and_duplicates_are_dropped() {
+        let candidates = ["SELECT", "sel", "selected", "SELECT", "DELETE"]
+            .iter()
+            .map(|text| Completion {
+                text: text.to_string(),
+                kind: CompletionKind::KEYWORD,
+            })
+            .collect();
+        let ranked: Vec<String> = rank(candidates, "sel")
+            .into_iter()
+            .map(|c| c.text)
+            .collect();
+        // the exact word typed isn't suggested
+        assert_eq!(ranked, vec!["SELECT", "selected"]);
+    }
+
+    #[test]
+    fn columns_are_suggested_after_an_alias() {
+        let database = SqliteDatabase::open(PathBuf::from(":memory:"), false, true).unwrap();
+        database
+            .connection
+            .execute_batch("CREATE TABLE people (name TEXT, age INTEGER)")
+            .unwrap();
+        let mut state = SqliteDatabaseState::new(&database);
+        state.set_query("SELECT p. FROM people p".to_string());
+        state.current_query_cursor = (9, 0);
+        state.update_completion(true);
+        let items: Vec<(String, CompletionKind)> = state
+            .completion
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|c| (c.text, c.kind))
+            .collect();
+        assert_eq!(
+            items,
+            vec![
+                ("name".to_string(), CompletionKind::COLUMN),
+                ("age".to_string(), CompletionKind::COLUMN)
+            ]
+        );
+    }
+}
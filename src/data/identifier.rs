@@ -0,0 +1,151 @@
+/// SQLite's keywords, sorted, see https://www.sqlite.org/lang_keywords.html
+#[rustfmt::skip]
+const SQLITE_KEYWORDS: [&str; 147] = [
+    "ABORT", "ACTION", "ADD", "AFTER", "ALL", "ALTER", "ALWAYS", "ANALYZE", "AND", "AS", "ASC",
+    "ATTACH", "AUTOINCREMENT", "BEFORE", "BEGIN", "BETWEEN", "BY", "CASCADE", "CASE", "CAST",
+    "CHECK", "COLLATE", "COLUMN", "COMMIT", "CONFLICT", "CONSTRAINT", "CREATE", "CROSS", "CURRENT",
+    "CURRENT_DATE", "CURRENT_TIME", "CURRENT_TIMESTAMP", "DATABASE", "DEFAULT", "DEFERRABLE",
+    "DEFERRED", "DELETE", "DESC", "DETACH", "DISTINCT", "DO", "DROP", "EACH", "ELSE", "END",
+    "ESCAPE", "EXCEPT", "EXCLUDE", "EXCLUSIVE", "EXISTS", "EXPLAIN", "FAIL", "FILTER", "FIRST",
+    "FOLLOWING", "FOR", "FOREIGN", "FROM", "FULL", "GENERATED", "GLOB", "GROUP", "GROUPS", "HAVING",
+    "IF", "IGNORE", "IMMEDIATE", "IN", "INDEX", "INDEXED", "INITIALLY", "INNER", "INSERT",
+    "INSTEAD", "INTERSECT", "INTO", "IS", "ISNULL", "JOIN", "KEY", "LAST", "LEFT", "LIKE", "LIMIT",
+    "MATCH", "MATERIALIZED", "NATURAL", "NO", "NOT", "NOTHING", "NOTNULL", "NULL", "NULLS", "OF",
+    "OFFSET", "ON", "OR", "ORDER", "OTHERS", "OUTER", "OVER", "PARTITION", "PLAN", "PRAGMA",
+    "PRECEDING", "PRIMARY", "QUERY", "RAISE", "RANGE", "RECURSIVE", "REFERENCES", "REGEXP",
+    "REINDEX", "RELEASE", "RENAME", "REPLACE", "RESTRICT", "RETURNING", "RIGHT", "ROLLBACK", "ROW",
+    "ROWS", "SAVEPOINT", "SELECT", "SET", "TABLE", "TEMP", "TEMPORARY", "THEN", "TIES", "TO",
+    "TRANSACTION", "TRIGGER", "UNBOUNDED", "UNION", "UNIQUE", "UPDATE", "USING", "VACUUM", "VALUES",
+    "VIEW", "VIRTUAL", "WHEN", "WHERE", "WINDOW", "WITH", "WITHOUT",
+];
+
+/// Quotes `name` as an SQL identifier, doubling any quotes inside it. The
+/// result is safe to put into generated SQL whatever the name contains.
+pub fn quote_identifier(name: &str) -> String {
+    return format!("\"{}\"", name.replace('"', "\"\""));
+}
+
+/// Like `quote_identifier` but leaves plain names such as `users` alone, for
+/// SQL the user gets to see and edit. Keywords are always quoted.
+pub fn quote_identifier_if_needed(name: &str) -> String {
+    if needs_quoting(name) {
+        return quote_identifier(name);
+    }
+    return name.to_string();
+}
+
+/// `schema.name`, quoted where needed. The `main` schema is left out.
+pub fn qualified_name(schema: &str, name: &str) -> String {
+    if schema == "main" {
+        return quote_identifier_if_needed(name);
+    }
+    return format!(
+        "{}.{}",
+        quote_identifier_if_needed(schema),
+        quote_identifier_if_needed(name)
+    );
+}
+
+fn needs_quoting(name: &str) -> bool {
+    let mut chars = name.chars();
+    let Some(first) = chars.next() else {
+        return true;
+    };
+    if !(first.is_ascii_alphabetic() || first == '_') {
+        return true;
+    }
+    if !chars.all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return true;
+    }
+    let upper = name.to_ascii_uppercase();
+    return SQLITE_KEYWORDS.binary_search(&upper.as_str()).is_ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use rusqlite::Connection;
+
+    use super::*;
+    use crate::data::sqlite_table::SqliteTable;
+
+    const ODD_NAMES: [&str; 8] = [
+        "order",
+        "my-table",
+        "with space",
+        "quote\"inside",
+        "1starts_with_digit",
+        "select",
+        "ünïcödé",
+        "semi;colon",
+    ];
+
+    #[test]
+    fn quotes_and_escapes() {
+        assert_eq!(quote_identifier("users"), "\"users\"");
+        assert_eq!(quote_identifier("my-table"), "\"my-table\"");
+        assert_eq!(quote_identifier("a\"b"), "\"a\"\"b\"");
+        assert_eq!(quote_identifier("\""), "\"\"\"\"");
+        assert_eq!(quote_identifier(""), "\"\"");
+    }
+
+    #[test]
+    fn quotes_only_when_needed() {
+        assert_eq!(quote_identifier_if_needed("users"), "users");
+        assert_eq!(quote_identifier_if_needed("_private2"), "_private2");
+        assert_eq!(quote_identifier_if_needed("order"), "\"order\"");
+        assert_eq!(quote_identifier_if_needed("Order"), "\"Order\"");
+        assert_eq!(quote_identifier_if_needed("temp"), "\"temp\"");
+        for name in ["id", "name", "type", "value", "year", "user", "data"] {
+            assert_eq!(quote_identifier_if_needed(name), name);
+        }
+        assert_eq!(quote_identifier_if_needed("my-table"), "\"my-table\"");
+        assert_eq!(quote_identifier_if_needed("with space"), "\"with space\"");
+        assert_eq!(quote_identifier_if_needed("2fast"), "\"2fast\"");
+        assert_eq!(quote_identifier_if_needed("ünïcödé"), "\"ünïcödé\"");
+        assert_eq!(quote_identifier_if_needed(""), "\"\"");
+    }
+
+    #[test]
+    fn qualifies_names_outside_main() {
+        assert_eq!(qualified_name("main", "users"), "users");
+        assert_eq!(qualified_name("main", "my-table"), "\"my-table\"");
+        assert_eq!(qualified_name("aux", "users"), "aux.users");
+        assert_eq!(
+            qualified_name("other db", "table"),
+            "\"other db\".\"table\""
+        );
+    }
+
+    #[test]
+    fn odd_names_round_trip_through_sqlite() {
+        let connection = Rc::new(Connection::open_in_memory().unwrap());
+        for name in ODD_NAMES {
+            connection
+                .execute_batch(&format!(
+                    "CREATE TABLE {} ({} INTEGER PRIMARY KEY, {} TEXT); INSERT INTO {} VALUES (1, 'x');",
+                    quote_identifier(name),
+                    quote_identifier(&format!("{} id", name)),
+                    quote_identifier(name),
+                    quote_identifier(name)
+                ))
+                .unwrap();
+
+            for quoted in [quote_identifier(name), qualified_name("main", name)] {
+                let count: i64 = connection
+                    .query_row(&format!("SELECT count(*) FROM {}", quoted), [], |row| {
+                        row.get(0)
+                    })
+                    .unwrap();
+                assert_eq!(count, 1, "{}", quoted);
+            }
+
+            let table = SqliteTable::new(name.to_string(), connection.clone());
+            let columns: Vec<String> = table.columns().unwrap().into_keys().collect();
+            assert_eq!(columns, vec![format!("{} id", name), name.to_string()]);
+            assert_eq!(table.row_count().unwrap(), 1);
+            assert!(table.create_sql().unwrap().is_some());
+        }
+    }
+}
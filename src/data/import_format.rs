@@ -0,0 +1,712 @@
+use std::{fs, path::Path, rc::Rc};
+
+use clap::ValueEnum;
+use rusqlite::{Connection, types::Value};
+
+use super::{
+    identifier::{quote_identifier, quote_identifier_if_needed},
+    sqlite_table::{SqliteColumn, SqliteTable},
+    table_builder::{ColumnDefinition, TableBuilder},
+};
+use crate::error::AppError;
+
+/// Rows looked at to pick the type of a new table's columns.
+const SAMPLE_ROWS: usize = 200;
+
+/// Rows inserted per transaction.
+pub const BATCH_SIZE: usize = 500;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+#[value(rename_all = "lower")]
+#[allow(clippy::upper_case_acronyms)]
+pub enum ImportFormat {
+    /// RFC 4180, the first row holds the column names.
+    CSV,
+    /// Tab separated with `\t`, `\n`, `\r` and `\\` escapes, as exported.
+    TSV,
+    /// An array of objects, or one object per line.
+    JSON,
+}
+impl ImportFormat {
+    pub const ALL: [ImportFormat; 3] = [ImportFormat::CSV, ImportFormat::TSV, ImportFormat::JSON];
+
+    /// The format a file's extension stands for, CSV when it is unknown.
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        return match extension.as_str() {
+            "tsv" | "tab" => ImportFormat::TSV,
+            "json" | "jsonl" | "ndjson" => ImportFormat::JSON,
+            _ => ImportFormat::CSV,
+        };
+    }
+}
+
+/// The contents of an import file. Rows may have more or fewer values than
+/// there are columns, inserting such a row reports it.
+#[derive(Debug)]
+pub struct ImportData {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+/// Reads the whole file at `path`. Empty CSV and TSV fields become NULL.
+pub fn read_file(path: &Path, format: ImportFormat) -> Result<ImportData, AppError> {
+    let content = fs::read_to_string(path)?;
+    let content = content.strip_prefix('\u{feff}').unwrap_or(&content);
+    let data = match format {
+        ImportFormat::CSV => delimited_data(parse_csv(content)?),
+        ImportFormat::TSV => delimited_data(parse_tsv(content)),
+        ImportFormat::JSON => parse_json(content)?,
+    };
+    if data.columns.is_empty() {
+        return Err(AppError::UI(format!(
+            "{} has no columns",
+            path.to_string_lossy()
+        )));
+    }
+    return Ok(data);
+}
+
+fn delimited_data(records: Vec<Vec<String>>) -> ImportData {
+    let mut records = records.into_iter();
+    let columns = unique_names(records.next().unwrap_or_default());
+    let rows = records
+        .map(|record| {
+            return record
+                .into_iter()
+                .map(|field| {
+                    if field.is_empty() {
+                        return Value::Null;
+                    }
+                    return Value::Text(field);
+                })
+                .collect();
+        })
+        .collect();
+    return ImportData { columns, rows };
+}
+
+/// Names every column, empty ones after their position, and numbers
+/// repeated ones so each can be told apart.
+fn unique_names(names: Vec<String>) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::new();
+    for (index, name) in names.into_iter().enumerate() {
+        let name = name.trim();
+        let base = if name.is_empty() {
+            format!("column{}", index + 1)
+        } else {
+            name.to_string()
+        };
+        let mut candidate = base.clone();
+        let mut suffix = 2;
+        while unique.iter().any(|n| n.eq_ignore_ascii_case(&candidate)) {
+            candidate = format!("{}_{}", base, suffix);
+            suffix += 1;
+        }
+        unique.push(candidate);
+    }
+    return unique;
+}
+
+/// Records of RFC 4180 text. Quoted fields may hold commas, quotes written
+/// twice and line breaks. Empty lines are skipped.
+fn parse_csv(content: &str) -> Result<Vec<Vec<String>>, AppError> {
+    let mut records = Vec::new();
+    let mut record: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => quoted = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => (),
+            '\n' | '\r' => {
+                record.push(std::mem::take(&mut field));
+                if record.len() > 1 || !record[0].is_empty() {
+                    records.push(std::mem::take(&mut record));
+                } else {
+                    record.clear();
+                }
+            }
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(AppError::UI(format!(
+            "the quoted field in record {} isn't closed",
+            records.len() + 1
+        )));
+    }
+    if !record.is_empty() || !field.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    return Ok(records);
+}
+
+fn parse_tsv(content: &str) -> Vec<Vec<String>> {
+    return content
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.split('\t').map(unescape_tsv).collect())
+        .collect();
+}
+
+fn unescape_tsv(field: &str) -> String {
+    let mut text = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => text.push('\t'),
+            Some('n') => text.push('\n'),
+            Some('r') => text.push('\r'),
+            Some(other) => text.push(other),
+            None => text.push('\\'),
+        }
+    }
+    return text;
+}
+
+/// An array of objects, or JSON lines. The columns are the keys in the order
+/// they first appear, a key missing from an object is NULL.
+fn parse_json(content: &str) -> Result<ImportData, AppError> {
+    let invalid = |e: serde_json::Error| AppError::UI(format!("invalid JSON: {}", e));
+    let objects: Vec<serde_json::Value> = if content.trim_start().starts_with('[') {
+        serde_json::from_str(content).map_err(invalid)?
+    } else {
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()
+            .map_err(invalid)?
+    };
+    let mut columns: Vec<String> = Vec::new();
+    let mut maps = Vec::with_capacity(objects.len());
+    for (index, object) in objects.into_iter().enumerate() {
+        let serde_json::Value::Object(map) = object else {
+            return Err(AppError::UI(format!(
+                "row {}: not a JSON object",
+                index + 1
+            )));
+        };
+        for key in map.keys() {
+            if !columns.contains(key) {
+                columns.push(key.clone());
+            }
+        }
+        maps.push(map);
+    }
+    let rows = maps
+        .into_iter()
+        .map(|map| {
+            return columns
+                .iter()
+                .map(|column| map.get(column).map_or(Value::Null, sqlite_value))
+                .collect();
+        })
+        .collect();
+    return Ok(ImportData { columns, rows });
+}
+
+/// Arrays and objects are stored as their JSON text.
+fn sqlite_value(value: &serde_json::Value) -> Value {
+    return match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Integer(*b as i64),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Real(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        serde_json::Value::String(s) => Value::Text(s.clone()),
+        other => Value::Text(other.to_string()),
+    };
+}
+
+/// Column types SQLite gives a meaning to, narrowest first.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[allow(clippy::upper_case_acronyms)]
+enum InferredType {
+    INTEGER,
+    REAL,
+    TEXT,
+}
+
+fn value_type(value: &Value) -> Option<InferredType> {
+    return match value {
+        Value::Null => None,
+        Value::Integer(_) => Some(InferredType::INTEGER),
+        Value::Real(_) => Some(InferredType::REAL),
+        Value::Text(text) => Some(text_type(text)),
+        Value::Blob(_) => Some(InferredType::TEXT),
+    };
+}
+
+/// Numbers with leading zeros, e.g. postal codes, stay text so the zeros
+/// aren't lost.
+fn text_type(text: &str) -> InferredType {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    if digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.") {
+        return InferredType::TEXT;
+    }
+    if text.parse::<i64>().is_ok() {
+        return InferredType::INTEGER;
+    }
+    if text.parse::<f64>().is_ok_and(f64::is_finite) {
+        return InferredType::REAL;
+    }
+    return InferredType::TEXT;
+}
+
+/// Columns for a new table holding `data`, typed after the first rows.
+/// Columns without any value in them are TEXT.
+pub fn infer_columns(data: &ImportData) -> Vec<SqliteColumn> {
+    return data
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            let inferred = data
+                .rows
+                .iter()
+                .take(SAMPLE_ROWS)
+                .filter_map(|row| row.get(index).and_then(value_type))
+                .max()
+                .unwrap_or(InferredType::TEXT);
+            let declared_type = match inferred {
+                InferredType::INTEGER => "INTEGER",
+                InferredType::REAL => "REAL",
+                InferredType::TEXT => "TEXT",
+            };
+            return SqliteColumn::new(
+                name.clone(),
+                declared_type.to_string(),
+                false,
+                Value::Null,
+                0,
+            );
+        })
+        .collect();
+}
+
+/// Where the rows go.
+#[derive(Debug)]
+#[allow(non_camel_case_types)]
+pub enum ImportTarget {
+    /// The table doesn't exist and is created with these columns.
+    NEW_TABLE(Vec<SqliteColumn>),
+    /// The file's columns that match one of the table's by name, the others
+    /// are left out.
+    EXISTING_TABLE {
+        mapped: Vec<(usize, String)>,
+        ignored: Vec<String>,
+    },
+}
+
+/// A row that wasn't inserted.
+#[derive(Debug)]
+pub struct RowError {
+    /// 1 based, not counting the header.
+    pub row: usize,
+    pub message: String,
+}
+
+/// An import in progress, run a batch at a time so the UI can show its
+/// progress.
+pub struct Import {
+    pub table: String,
+    pub target: ImportTarget,
+    pub data: ImportData,
+    /// Rows done so far, inserted or failed.
+    pub next_row: usize,
+    pub inserted: usize,
+    pub errors: Vec<RowError>,
+    table_created: bool,
+}
+impl Import {
+    /// Maps `data` onto `table`, or plans to create it. Nothing is written
+    /// yet.
+    pub fn prepare(
+        connection: &Rc<Connection>,
+        table: &str,
+        data: ImportData,
+    ) -> Result<Self, AppError> {
+        let table = table.trim();
+        if table.is_empty() {
+            return Err(AppError::UI("enter the name of the table".to_string()));
+        }
+        let existing = SqliteTable::new(table.to_string(), connection.clone()).columns()?;
+        let target = if existing.is_empty() {
+            ImportTarget::NEW_TABLE(infer_columns(&data))
+        } else {
+            let mut mapped = Vec::new();
+            let mut ignored = Vec::new();
+            for (index, column) in data.columns.iter().enumerate() {
+                match existing
+                    .keys()
+                    .find(|name| name.eq_ignore_ascii_case(column))
+                {
+                    Some(name) => mapped.push((index, name.clone())),
+                    None => ignored.push(column.clone()),
+                }
+            }
+            if mapped.is_empty() {
+                return Err(AppError::SCHEMA(format!(
+                    "none of the file's columns ({}) are in {}",
+                    data.columns.join(", "),
+                    table
+                )));
+            }
+            ImportTarget::EXISTING_TABLE { mapped, ignored }
+        };
+        return Ok(Self {
+            table: table.to_string(),
+            target,
+            data,
+            next_row: 0,
+            inserted: 0,
+            errors: Vec::new(),
+            table_created: false,
+        });
+    }
+
+    /// What the import is going to do, a line each.
+    pub fn plan(&self) -> Vec<String> {
+        let table = quote_identifier_if_needed(&self.table);
+        let mut lines = vec![format!(
+            "{} rows, {} columns",
+            self.data.rows.len(),
+            self.data.columns.len()
+        )];
+        match &self.target {
+            ImportTarget::NEW_TABLE(columns) => {
+                let columns: Vec<String> = columns
+                    .iter()
+                    .map(|c| format!("{} {}", c.name, c.declared_type))
+                    .collect();
+                lines.push(format!("Creates {} ({})", table, columns.join(", ")));
+            }
+            ImportTarget::EXISTING_TABLE { mapped, ignored } => {
+                let pairs: Vec<String> = mapped
+                    .iter()
+                    .map(|(index, column)| format!("{} → {}", self.data.columns[*index], column))
+                    .collect();
+                lines.push(format!("Inserts into {}: {}", table, pairs.join(", ")));
+                if !ignored.is_empty() {
+                    lines.push(format!(
+                        "Not in the table, left out: {}",
+                        ignored.join(", ")
+                    ));
+                }
+            }
+        }
+        return lines;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        return self.next_row >= self.data.rows.len() && self.table_created_if_needed();
+    }
+
+    fn table_created_if_needed(&self) -> bool {
+        return self.table_created || matches!(self.target, ImportTarget::EXISTING_TABLE { .. });
+    }
+
+    /// Fraction of the rows done, for the progress bar.
+    pub fn progress(&self) -> f64 {
+        if self.data.rows.is_empty() {
+            return 1.0;
+        }
+        return self.next_row as f64 / self.data.rows.len() as f64;
+    }
+
+    /// Inserts the next `BATCH_SIZE` rows in a transaction of their own, a
+    /// savepoint so it also works inside an open one. Rows SQLite refuses
+    /// are recorded and skipped. The first batch creates the table. When the
+    /// batch fails as a whole it is rolled back and the counts with it.
+    pub fn run_batch(&mut self, connection: &Connection) -> Result<(), AppError> {
+        let (inserted, errors, table_created) =
+            (self.inserted, self.errors.len(), self.table_created);
+        connection.execute_batch("SAVEPOINT import")?;
+        let result = self.insert_batch(connection);
+        match result {
+            Ok(()) => connection.execute_batch("RELEASE import")?,
+            Err(_) => {
+                connection.execute_batch("ROLLBACK TO import; RELEASE import")?;
+                self.inserted = inserted;
+                self.errors.truncate(errors);
+                self.table_created = table_created;
+            }
+        }
+        return result;
+    }
+
+    fn insert_batch(&mut self, connection: &Connection) -> Result<(), AppError> {
+        if let ImportTarget::NEW_TABLE(columns) = &self.target
+            && !self.table_created
+        {
+            let builder = TableBuilder {
+                name: self.table.clone(),
+                columns: columns.iter().map(ColumnDefinition::from).collect(),
+                ..TableBuilder::new()
+            };
+            builder.create(connection)?;
+            self.table_created = true;
+        }
+        let (sources, names): (Vec<usize>, Vec<String>) = match &self.target {
+            ImportTarget::NEW_TABLE(columns) => columns
+                .iter()
+                .enumerate()
+                .map(|(index, c)| (index, quote_identifier(&c.name)))
+                .unzip(),
+            ImportTarget::EXISTING_TABLE { mapped, .. } => mapped
+                .iter()
+                .map(|(index, name)| (*index, quote_identifier(name)))
+                .unzip(),
+        };
+        let sql = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            quote_identifier(&self.table),
+            names.join(", "),
+            vec!["?"; names.len()].join(", ")
+        );
+        let mut stmt = connection.prepare_cached(&sql)?;
+        let expected = self.data.columns.len();
+        let end = (self.next_row + BATCH_SIZE).min(self.data.rows.len());
+        for index in self.next_row..end {
+            let row = &self.data.rows[index];
+            let result = if row.len() != expected {
+                Err(AppError::UI(format!(
+                    "{} values, the file has {} columns",
+                    row.len(),
+                    expected
+                )))
+            } else {
+                stmt.execute(rusqlite::params_from_iter(sources.iter().map(|i| &row[*i])))
+                    .map_err(AppError::from)
+            };
+            match result {
+                Ok(_) => self.inserted += 1,
+                Err(AppError::INTERRUPTED) => return Err(AppError::INTERRUPTED),
+                Err(e) => self.errors.push(RowError {
+                    row: index + 1,
+                    message: e.to_string(),
+                }),
+            }
+        }
+        self.next_row = end;
+        return Ok(());
+    }
+
+    /// How it went, for the end of the report.
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} row{} imported into {}",
+            self.inserted,
+            if self.inserted == 1 { "" } else { "s" },
+            quote_identifier_if_needed(&self.table)
+        );
+        if !self.errors.is_empty() {
+            summary.push_str(&format!(", {} failed", self.errors.len()));
+        }
+        let skipped = self.data.rows.len() - self.next_row;
+        if skipped > 0 {
+            summary.push_str(&format!(", {} not imported", skipped));
+        }
+        return summary;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::result_format::{BlobEncoding, ResultFormat, tests::export_from};
+
+    fn text(value: &str) -> Value {
+        return Value::Text(value.to_string());
+    }
+
+    /// `sql`'s result written in `format`, as a file to import would hold it.
+    fn exported(connection: &Connection, sql: &str, format: ResultFormat) -> String {
+        return export_from(connection, sql, format, BlobEncoding::HEX);
+    }
+
+    #[test]
+    fn parses_quoted_csv_fields() {
+        let records =
+            parse_csv("a,b,c\r\n\"x,y\",\"say \"\"hi\"\"\",\"two\r\nlines\"\n\n1,,3").unwrap();
+        assert_eq!(
+            records,
+            vec![
+                vec!["a", "b", "c"],
+                vec!["x,y", "say \"hi\"", "two\r\nlines"],
+                vec!["1", "", "3"],
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_unclosed_csv_quotes() {
+        let error = parse_csv("a\n1\n\"open\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the quoted field in record 3 isn't closed"
+        );
+    }
+
+    #[test]
+    fn csv_and_tsv_exports_read_back_unchanged() {
+        let connection = Connection::open_in_memory().unwrap();
+        let sql =
+            "SELECT 'a,b' AS \"x,y\", 'say \"hi\"' AS q, 'two\nlines' AS n, 'tab\there\\' AS t";
+        let expected = vec![
+            vec!["x,y", "q", "n", "t"],
+            vec!["a,b", "say \"hi\"", "two\nlines", "tab\there\\"],
+        ];
+        let csv = exported(&connection, sql, ResultFormat::CSV);
+        assert_eq!(parse_csv(&csv).unwrap(), expected);
+        let tsv = exported(&connection, sql, ResultFormat::TSV);
+        assert_eq!(parse_tsv(&tsv), expected);
+    }
+
+    #[test]
+    fn unescapes_tsv_fields() {
+        assert_eq!(unescape_tsv("a\\tb\\nc\\rd\\\\e"), "a\tb\nc\rd\\e");
+        assert_eq!(unescape_tsv("trailing\\"), "trailing\\");
+        assert_eq!(unescape_tsv("\\x"), "x");
+    }
+
+    #[test]
+    fn names_columns_uniquely() {
+        assert_eq!(
+            unique_names(vec![
+                "id".to_string(),
+                " ".to_string(),
+                "ID".to_string(),
+                "id".to_string()
+            ]),
+            vec!["id", "column2", "ID_2", "id_3"]
+        );
+    }
+
+    #[test]
+    fn parses_json_arrays_and_lines() {
+        let array = parse_json(r#"[{"b": 1, "a": true}, {"c": [1, 2], "a": null}]"#).unwrap();
+        assert_eq!(array.columns, vec!["b", "a", "c"]);
+        assert_eq!(
+            array.rows,
+            vec![
+                vec![Value::Integer(1), Value::Integer(1), Value::Null],
+                vec![Value::Null, Value::Null, text("[1,2]")],
+            ]
+        );
+        let lines = parse_json("{\"x\": 1.5}\n\n{\"x\": \"s\"}\n").unwrap();
+        assert_eq!(lines.columns, vec!["x"]);
+        assert_eq!(lines.rows, vec![vec![Value::Real(1.5)], vec![text("s")]]);
+        assert_eq!(
+            parse_json("[{\"x\": 1}, 2]").unwrap_err().to_string(),
+            "row 2: not a JSON object"
+        );
+    }
+
+    #[test]
+    fn infers_the_narrowest_type() {
+        assert!(text_type("12") == InferredType::INTEGER);
+        assert!(text_type("-3") == InferredType::INTEGER);
+        assert!(text_type("0") == InferredType::INTEGER);
+        assert!(text_type("0.5") == InferredType::REAL);
+        assert!(text_type("-1e3") == InferredType::REAL);
+        assert!(text_type("007") == InferredType::TEXT);
+        assert!(text_type("-01") == InferredType::TEXT);
+        assert!(text_type("inf") == InferredType::TEXT);
+        assert!(text_type("NaN") == InferredType::TEXT);
+        assert!(text_type("abc") == InferredType::TEXT);
+
+        let data = ImportData {
+            columns: vec!["i", "r", "t", "empty"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            rows: vec![
+                vec![text("1"), text("1"), text("1"), Value::Null],
+                vec![text("2"), text("2.5"), text("x"), Value::Null],
+            ],
+        };
+        let types: Vec<String> = infer_columns(&data)
+            .into_iter()
+            .map(|c| c.declared_type)
+            .collect();
+        assert_eq!(types, vec!["INTEGER", "REAL", "TEXT", "TEXT"]);
+    }
+
+    #[test]
+    fn imports_and_reports_failed_rows() {
+        let connection = Rc::new(Connection::open_in_memory().unwrap());
+        connection
+            .execute_batch("CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT NOT NULL)")
+            .unwrap();
+        let data = ImportData {
+            columns: vec!["ID".to_string(), "name".to_string(), "extra".to_string()],
+            rows: vec![
+                vec![text("1"), text("a"), Value::Null],
+                vec![text("2"), Value::Null, Value::Null],
+                vec![text("3")],
+            ],
+        };
+        let mut import = Import::prepare(&connection, "t", data).unwrap();
+        while !import.is_finished() {
+            import.run_batch(&connection).unwrap();
+        }
+        assert_eq!(import.inserted, 1);
+        let failed: Vec<usize> = import.errors.iter().map(|e| e.row).collect();
+        assert_eq!(failed, vec![2, 3]);
+        assert_eq!(import.summary(), "1 row imported into t, 2 failed");
+    }
+
+    #[test]
+    fn a_failed_batch_is_rolled_back_with_its_counts() {
+        let connection = Rc::new(Connection::open_in_memory().unwrap());
+        let data = ImportData {
+            columns: vec!["a".to_string()],
+            rows: vec![vec![text("1")]],
+        };
+        let mut import = Import::prepare(&connection, "t", data).unwrap();
+        // the INSERT finds the temporary table first and can't be prepared,
+        // after the batch already created `main.t`
+        connection
+            .execute_batch("CREATE TEMP TABLE t (other)")
+            .unwrap();
+        assert!(import.run_batch(&connection).is_err());
+        assert!(!import.table_created);
+        assert_eq!(
+            (import.inserted, import.errors.len(), import.next_row),
+            (0, 0, 0)
+        );
+        let tables: i64 = connection
+            .query_row(
+                "SELECT count(*) FROM main.sqlite_master WHERE name = 't'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(tables, 0);
+    }
+}
@@ -1,3 +1,22 @@
+pub mod cell_editor;
+pub mod change_detection;
+pub mod completion;
+pub mod identifier;
+pub mod import_format;
+pub mod object_tree;
+pub mod query_editor;
+pub mod query_history;
+pub mod query_parameters;
+pub mod query_plan;
+pub mod query_worker;
+pub mod result_export;
+pub mod result_format;
+pub mod result_navigation;
+pub mod row_editor;
+pub mod script;
 pub mod sqlite_database;
 pub mod sqlite_query;
 pub mod sqlite_table;
+pub mod table_builder;
+pub mod table_import;
+pub mod transaction;
@@ -0,0 +1,406 @@
+use std::{collections::HashSet, rc::Rc};
+
+use rusqlite::{Connection, Error};
+
+use super::{
+    identifier::{qualified_name, quote_identifier},
+    sqlite_table::SqliteTable,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum SchemaObjectKind {
+    TABLE,
+    VIEW,
+    INDEX,
+    TRIGGER,
+}
+impl SchemaObjectKind {
+    pub const ALL: [SchemaObjectKind; 4] = [
+        SchemaObjectKind::TABLE,
+        SchemaObjectKind::VIEW,
+        SchemaObjectKind::INDEX,
+        SchemaObjectKind::TRIGGER,
+    ];
+
+    /// Value of the `type` column in `sqlite_master`.
+    pub fn master_type(&self) -> &'static str {
+        return match self {
+            SchemaObjectKind::TABLE => "table",
+            SchemaObjectKind::VIEW => "view",
+            SchemaObjectKind::INDEX => "index",
+            SchemaObjectKind::TRIGGER => "trigger",
+        };
+    }
+
+    pub fn group_label(&self) -> &'static str {
+        return match self {
+            SchemaObjectKind::TABLE => "Tables",
+            SchemaObjectKind::VIEW => "Views",
+            SchemaObjectKind::INDEX => "Indexes",
+            SchemaObjectKind::TRIGGER => "Triggers",
+        };
+    }
+
+    fn from_master_type(value: &str) -> Option<Self> {
+        return Self::ALL.into_iter().find(|k| k.master_type() == value);
+    }
+}
+
+/// A row of some schema's `sqlite_master`.
+#[derive(Clone, Debug)]
+pub struct SchemaObject {
+    /// `main`, `temp` or the name of an attached database.
+    pub schema: String,
+    pub name: String,
+    pub kind: SchemaObjectKind,
+    /// Table an index or trigger belongs to, the object itself otherwise.
+    pub table_name: String,
+}
+impl SchemaObject {
+    /// Name to use in a query, prefixed with the schema outside of `main` and
+    /// quoted where needed.
+    pub fn qualified_name(&self) -> String {
+        return qualified_name(&self.schema, &self.name);
+    }
+
+    /// Tables and views can be queried, indexes and triggers can't.
+    pub fn is_queryable(&self) -> bool {
+        return matches!(self.kind, SchemaObjectKind::TABLE | SchemaObjectKind::VIEW);
+    }
+}
+
+/// Reads the schemas on the connection, attached ones included, and their
+/// objects. `temp` is left out while nothing was created in it.
+fn load_objects(connection: &Connection) -> Result<(Vec<String>, Vec<SchemaObject>), Error> {
+    let mut schemas: Vec<String> = connection
+        .prepare("SELECT name FROM pragma_database_list ORDER BY seq")?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+    let mut objects = Vec::new();
+    for schema in &schemas {
+        let sql = format!(
+            "SELECT type, name, tbl_name FROM {}.sqlite_master WHERE type IN ('table', 'view', 'index', 'trigger') AND name NOT LIKE 'sqlite_%' ORDER BY name",
+            quote_identifier(schema)
+        );
+        let mut stmt = connection.prepare(&sql)?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let kind: String = row.get(0)?;
+            let Some(kind) = SchemaObjectKind::from_master_type(&kind) else {
+                continue;
+            };
+            objects.push(SchemaObject {
+                schema: schema.clone(),
+                name: row.get(1)?,
+                kind,
+                table_name: row.get(2)?,
+            });
+        }
+    }
+    schemas.retain(|schema| schema != "temp" || objects.iter().any(|o| o.schema == "temp"));
+    return Ok((schemas, objects));
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum ObjectTreeNode {
+    SCHEMA(String),
+    GROUP(String, SchemaObjectKind),
+    /// Index into `ObjectTree::objects`.
+    OBJECT(usize),
+}
+
+pub struct ObjectTreeRow {
+    pub node: ObjectTreeNode,
+    pub depth: usize,
+    pub label: String,
+    /// `None` for leaves.
+    pub expanded: Option<bool>,
+}
+
+/// The left panel: objects grouped by kind, and by schema when databases are
+/// attached. Groups and schemas can be expanded and collapsed.
+pub struct ObjectTree {
+    pub objects: Vec<SchemaObject>,
+    schemas: Vec<String>,
+    /// Schemas as `(schema, None)` and groups as `(schema, Some(kind))`.
+    expanded: HashSet<(String, Option<SchemaObjectKind>)>,
+    /// Index into `rows()`.
+    pub selected: Option<usize>,
+}
+impl Default for ObjectTree {
+    /// Empty tree with the tables of `main` expanded.
+    fn default() -> Self {
+        return Self {
+            objects: Vec::new(),
+            schemas: Vec::new(),
+            expanded: HashSet::from([
+                ("main".to_string(), None),
+                ("main".to_string(), Some(SchemaObjectKind::TABLE)),
+            ]),
+            selected: None,
+        };
+    }
+}
+impl ObjectTree {
+    /// Reads the objects again, keeping what is expanded and the selected node
+    /// when it still exists.
+    pub fn reload(&mut self, connection: &Connection) -> Result<(), Error> {
+        let (schemas, objects) = load_objects(connection)?;
+        let selected = self.selected_node();
+        let selected_object = self
+            .selected_object()
+            .map(|o| (o.schema.clone(), o.name.clone()));
+        self.objects = objects;
+        self.schemas = schemas;
+        self.selected = match selected {
+            Some(ObjectTreeNode::OBJECT(_)) => {
+                // indices changed, find the object again by name
+                let (schema, name) = selected_object.unwrap();
+                let index = self
+                    .objects
+                    .iter()
+                    .position(|o| o.schema == schema && o.name == name);
+                index.and_then(|index| {
+                    self.rows()
+                        .iter()
+                        .position(|r| r.node == ObjectTreeNode::OBJECT(index))
+                })
+            }
+            Some(node) => self.rows().iter().position(|r| r.node == node),
+            None => None,
+        };
+        return Ok(());
+    }
+
+    /// Selects `name` in `schema`, expanding its group so it is visible.
+    pub fn select_object(&mut self, schema: &str, name: &str) {
+        let Some(index) = self
+            .objects
+            .iter()
+            .position(|o| o.schema == schema && o.name == name)
+        else {
+            return;
+        };
+        let kind = self.objects[index].kind;
+        self.expanded.insert((schema.to_string(), None));
+        self.expanded.insert((schema.to_string(), Some(kind)));
+        self.selected = self
+            .rows()
+            .iter()
+            .position(|r| r.node == ObjectTreeNode::OBJECT(index));
+    }
+
+    /// Visible rows, top to bottom.
+    pub fn rows(&self) -> Vec<ObjectTreeRow> {
+        let mut rows = Vec::new();
+        // only show the schema level once something is attached
+        let show_schemas = self.schemas.len() > 1;
+        for schema in &self.schemas {
+            let mut depth = 0;
+            if show_schemas {
+                let expanded = self.expanded.contains(&(schema.clone(), None));
+                rows.push(ObjectTreeRow {
+                    node: ObjectTreeNode::SCHEMA(schema.clone()),
+                    depth,
+                    label: schema.clone(),
+                    expanded: Some(expanded),
+                });
+                if !expanded {
+                    continue;
+                }
+                depth += 1;
+            }
+            for kind in SchemaObjectKind::ALL {
+                let members: Vec<usize> = (0..self.objects.len())
+                    .filter(|i| self.objects[*i].schema == *schema && self.objects[*i].kind == kind)
+                    .collect();
+                let expanded = self.expanded.contains(&(schema.clone(), Some(kind)));
+                rows.push(ObjectTreeRow {
+                    node: ObjectTreeNode::GROUP(schema.clone(), kind),
+                    depth,
+                    label: format!("{} ({})", kind.group_label(), members.len()),
+                    expanded: Some(expanded),
+                });
+                if !expanded {
+                    continue;
+                }
+                for index in members {
+                    let object = &self.objects[index];
+                    let label = match object.kind {
+                        SchemaObjectKind::INDEX | SchemaObjectKind::TRIGGER => {
+                            format!("{} ({})", object.name, object.table_name)
+                        }
+                        _ => object.name.clone(),
+                    };
+                    rows.push(ObjectTreeRow {
+                        node: ObjectTreeNode::OBJECT(index),
+                        depth: depth + 1,
+                        label,
+                        expanded: None,
+                    });
+                }
+            }
+        }
+        return rows;
+    }
+
+    pub fn selected_node(&self) -> Option<ObjectTreeNode> {
+        let selected = self.selected?;
+        return self.rows().into_iter().nth(selected).map(|r| r.node);
+    }
+
+    pub fn selected_object(&self) -> Option<&SchemaObject> {
+        return match self.selected_node()? {
+            ObjectTreeNode::OBJECT(index) => self.objects.get(index),
+            _ => None,
+        };
+    }
+
+    /// Moves the selection by `offset` rows, wrapping around at both ends.
+    pub fn move_selection(&mut self, offset: isize) {
+        let count = self.rows().len();
+        if count == 0 {
+            self.selected = None;
+            return;
+        }
+        self.selected = Some(match self.selected {
+            None => 0,
+            Some(selected) => (selected as isize + offset).rem_euclid(count as isize) as usize,
+        });
+    }
+
+    /// Expands or collapses the selected schema or group.
+    pub fn toggle_selected(&mut self) {
+        let Some(key) = self.selected_key() else {
+            return;
+        };
+        if !self.expanded.remove(&key) {
+            self.expanded.insert(key);
+        }
+    }
+
+    pub fn expand_selected(&mut self) {
+        if let Some(key) = self.selected_key() {
+            self.expanded.insert(key);
+        }
+    }
+
+    /// Collapses the selected schema or group, or jumps from an object to its
+    /// group.
+    pub fn collapse_selected(&mut self) {
+        match self.selected_node() {
+            Some(ObjectTreeNode::OBJECT(index)) => {
+                let object = &self.objects[index];
+                let group = ObjectTreeNode::GROUP(object.schema.clone(), object.kind);
+                self.selected = self.rows().iter().position(|r| r.node == group);
+            }
+            Some(_) => {
+                if let Some(key) = self.selected_key() {
+                    self.expanded.remove(&key);
+                }
+            }
+            None => (),
+        }
+    }
+
+    fn selected_key(&self) -> Option<(String, Option<SchemaObjectKind>)> {
+        return match self.selected_node()? {
+            ObjectTreeNode::SCHEMA(schema) => Some((schema, None)),
+            ObjectTreeNode::GROUP(schema, kind) => Some((schema, Some(kind))),
+            ObjectTreeNode::OBJECT(_) => None,
+        };
+    }
+
+    /// The table or view an object is about, for the schema view. Indexes and
+    /// triggers give the table they belong to.
+    pub fn selected_table(&self, connection: &Rc<Connection>) -> Option<SqliteTable> {
+        let object = self.selected_object()?;
+        return Some(SqliteTable::in_schema(
+            object.schema.clone(),
+            object.table_name.clone(),
+            connection.clone(),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection() -> Connection {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE b (x);
+                 CREATE TABLE a (x);
+                 CREATE VIEW v AS SELECT * FROM a;
+                 CREATE INDEX a_x ON a (x);",
+            )
+            .unwrap();
+        return connection;
+    }
+
+    fn load(connection: &Connection) -> ObjectTree {
+        let mut tree = ObjectTree::default();
+        tree.reload(connection).unwrap();
+        return tree;
+    }
+
+    fn labels(tree: &ObjectTree) -> Vec<String> {
+        return tree
+            .rows()
+            .into_iter()
+            .map(|r| format!("{}{}", "  ".repeat(r.depth), r.label))
+            .collect();
+    }
+
+    #[test]
+    fn objects_are_grouped_by_kind() {
+        let mut tree = load(&connection());
+        assert_eq!(
+            labels(&tree),
+            vec![
+                "Tables (2)",
+                "  a",
+                "  b",
+                "Views (1)",
+                "Indexes (1)",
+                "Triggers (0)"
+            ]
+        );
+        tree.selected = Some(2);
+        assert_eq!(tree.selected_object().unwrap().name, "b");
+        tree.collapse_selected();
+        assert_eq!(tree.selected, Some(0));
+        tree.collapse_selected();
+        assert_eq!(labels(&tree)[..2], ["Tables (2)", "Views (1)"]);
+    }
+
+    #[test]
+    fn schemas_are_shown_once_a_database_is_attached() {
+        let connection = connection();
+        let mut tree = load(&connection);
+        tree.select_object("main", "a_x");
+        connection
+            .execute_batch("ATTACH ':memory:' AS att; CREATE TABLE att.c (x);")
+            .unwrap();
+        tree.reload(&connection).unwrap();
+        assert_eq!(labels(&tree)[0], "main");
+        assert_eq!(labels(&tree).last().unwrap(), "att");
+        // the selection follows the object to its new row
+        assert_eq!(tree.selected_object().unwrap().name, "a_x");
+        tree.select_object("att", "c");
+        assert_eq!(tree.selected_object().unwrap().qualified_name(), "att.c");
+    }
+
+    #[test]
+    fn moving_the_selection_wraps_around() {
+        let mut tree = load(&connection());
+        tree.move_selection(1);
+        assert_eq!(tree.selected, Some(0));
+        tree.move_selection(-1);
+        assert_eq!(tree.selected, Some(5));
+    }
+}
@@ -0,0 +1,242 @@
+use super::sqlite_database::SqliteDatabaseState;
+
+/// Lines moved by PageUp/PageDown in the query editor.
+pub const PAGE_LINES: usize = 10;
+
+/// Text editing for the query tool. `current_query_cursor` is `(column, row)`
+/// counted in characters, with rows separated by `\n`.
+impl SqliteDatabaseState {
+    pub fn query_lines(&self) -> Vec<&str> {
+        return self.current_query.split('\n').collect();
+    }
+
+    pub fn set_query(&mut self, query: String) {
+        self.current_query = query;
+        let lines = self.query_lines();
+        let row = lines.len() - 1;
+        let column = lines[row].chars().count();
+        self.current_query_cursor = (column, row);
+    }
+
+    fn line_length(&self, row: usize) -> usize {
+        return self
+            .query_lines()
+            .get(row)
+            .map(|l| l.chars().count())
+            .unwrap_or(0);
+    }
+
+    /// Byte offset of a `(column, row)` position inside `current_query`.
+    fn byte_index(&self, (column, row): (usize, usize)) -> usize {
+        let mut index = 0;
+        for (i, line) in self.current_query.split('\n').enumerate() {
+            if i == row {
+                return index
+                    + line
+                        .char_indices()
+                        .nth(column)
+                        .map(|(b, _)| b)
+                        .unwrap_or(line.len());
+            }
+            index += line.len() + 1;
+        }
+        return self.current_query.len();
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        let index = self.byte_index(self.current_query_cursor);
+        self.current_query.insert(index, c);
+        if c == '\n' {
+            self.current_query_cursor = (0, self.current_query_cursor.1 + 1);
+        } else {
+            self.current_query_cursor.0 += 1;
+        }
+    }
+
+    pub fn insert_str(&mut self, text: &str) {
+        for c in text.chars() {
+            self.insert_char(c);
+        }
+    }
+
+    pub fn delete_backward(&mut self) {
+        let (column, row) = self.current_query_cursor;
+        if column == 0 && row == 0 {
+            return;
+        }
+        self.move_left();
+        let index = self.byte_index(self.current_query_cursor);
+        self.current_query.remove(index);
+    }
+
+    pub fn delete_forward(&mut self) {
+        let index = self.byte_index(self.current_query_cursor);
+        if index < self.current_query.len() {
+            self.current_query.remove(index);
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        let (column, row) = self.current_query_cursor;
+        if column > 0 {
+            self.current_query_cursor.0 -= 1;
+        } else if row > 0 {
+            self.current_query_cursor = (self.line_length(row - 1), row - 1);
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        let (column, row) = self.current_query_cursor;
+        if column < self.line_length(row) {
+            self.current_query_cursor.0 += 1;
+        } else if row + 1 < self.query_lines().len() {
+            self.current_query_cursor = (0, row + 1);
+        }
+    }
+
+    pub fn move_up(&mut self, lines: usize) {
+        let (column, row) = self.current_query_cursor;
+        let row = row.saturating_sub(lines);
+        self.current_query_cursor = (column.min(self.line_length(row)), row);
+    }
+
+    pub fn move_down(&mut self, lines: usize) {
+        let (column, row) = self.current_query_cursor;
+        let row = (row + lines).min(self.query_lines().len() - 1);
+        self.current_query_cursor = (column.min(self.line_length(row)), row);
+    }
+
+    pub fn move_home(&mut self) {
+        self.current_query_cursor.0 = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.current_query_cursor.0 = self.line_length(self.current_query_cursor.1);
+    }
+
+    /// Moves to the start of the previous word, crossing line breaks.
+    pub fn move_word_left(&mut self) {
+        let chars: Vec<char> = self.current_query.chars().collect();
+        let mut index = self.current_query[..self.byte_index(self.current_query_cursor)]
+            .chars()
+            .count();
+        while index > 0 && !is_word_char(chars[index - 1]) {
+            index -= 1;
+        }
+        while index > 0 && is_word_char(chars[index - 1]) {
+            index -= 1;
+        }
+        self.current_query_cursor = cursor_at(&chars, index);
+    }
+
+    /// Moves past the end of the next word, crossing line breaks.
+    pub fn move_word_right(&mut self) {
+        let chars: Vec<char> = self.current_query.chars().collect();
+        let mut index = self.current_query[..self.byte_index(self.current_query_cursor)]
+            .chars()
+            .count();
+        while index < chars.len() && !is_word_char(chars[index]) {
+            index += 1;
+        }
+        while index < chars.len() && is_word_char(chars[index]) {
+            index += 1;
+        }
+        self.current_query_cursor = cursor_at(&chars, index);
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    return c.is_alphanumeric() || c == '_';
+}
+
+fn cursor_at(chars: &[char], index: usize) -> (usize, usize) {
+    let mut cursor = (0, 0);
+    for c in &chars[..index] {
+        if *c == '\n' {
+            cursor = (0, cursor.1 + 1);
+        } else {
+            cursor.0 += 1;
+        }
+    }
+    return cursor;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::super::sqlite_database::SqliteDatabase;
+    use super::*;
+
+    fn editor(query: &str) -> SqliteDatabaseState {
+        let database = SqliteDatabase::open(PathBuf::from(":memory:"), false, true).unwrap();
+        let mut state = SqliteDatabaseState::new(&database);
+        state.set_query(query.to_string());
+        return state;
+    }
+
+    #[test]
+    fn set_query_puts_the_cursor_at_the_end() {
+        assert_eq!(editor("SELECT 1\nFROM t").current_query_cursor, (6, 1));
+        assert_eq!(editor("").current_query_cursor, (0, 0));
+    }
+
+    #[test]
+    fn edits_count_characters_not_bytes() {
+        let mut state = editor("é\nü");
+        state.current_query_cursor = (1, 0);
+        state.insert_char('x');
+        assert_eq!(state.current_query, "éx\nü");
+        state.delete_backward();
+        state.delete_backward();
+        assert_eq!(state.current_query, "\nü");
+        state.delete_forward();
+        assert_eq!(state.current_query, "ü");
+        assert_eq!(state.current_query_cursor, (0, 0));
+    }
+
+    #[test]
+    fn backspace_and_arrows_cross_line_breaks() {
+        let mut state = editor("ab\ncd");
+        state.current_query_cursor = (0, 1);
+        state.move_left();
+        assert_eq!(state.current_query_cursor, (2, 0));
+        state.move_right();
+        assert_eq!(state.current_query_cursor, (0, 1));
+        state.delete_backward();
+        assert_eq!(state.current_query, "abcd");
+        assert_eq!(state.current_query_cursor, (2, 0));
+    }
+
+    #[test]
+    fn vertical_moves_clamp_the_column() {
+        let mut state = editor("long line\nx\nanother");
+        state.current_query_cursor = (8, 0);
+        state.move_down(1);
+        assert_eq!(state.current_query_cursor, (1, 1));
+        state.move_down(PAGE_LINES);
+        assert_eq!(state.current_query_cursor, (1, 2));
+        state.move_up(PAGE_LINES);
+        assert_eq!(state.current_query_cursor, (1, 0));
+    }
+
+    #[test]
+    fn word_jumps_skip_punctuation_and_cross_lines() {
+        let mut state = editor("SELECT a_b,\n  c FROM t");
+        state.current_query_cursor = (0, 0);
+        state.move_word_right();
+        assert_eq!(state.current_query_cursor, (6, 0));
+        state.move_word_right();
+        assert_eq!(state.current_query_cursor, (10, 0));
+        state.move_word_right();
+        assert_eq!(state.current_query_cursor, (3, 1));
+        state.move_word_left();
+        assert_eq!(state.current_query_cursor, (2, 1));
+        state.move_word_left();
+        assert_eq!(state.current_query_cursor, (7, 0));
+        state.move_end();
+        state.move_home();
+        state.move_word_left();
+        assert_eq!(state.current_query_cursor, (0, 0));
+    }
+}
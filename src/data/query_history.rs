@@ -0,0 +1,343 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use super::sqlite_database::{SqliteDatabaseState, SqliteDatabaseStateMode};
+
+/// Entries kept in memory and in the history file.
+pub const HISTORY_CAPACITY: usize = 1000;
+
+pub struct HistoryEntry {
+    pub query: String,
+    pub executed_at: SystemTime,
+    pub duration: Duration,
+    /// Rows returned or changed, `None` when the query failed.
+    pub rows: Option<usize>,
+    pub error: Option<String>,
+}
+impl HistoryEntry {
+    pub fn is_success(&self) -> bool {
+        return self.error.is_none();
+    }
+
+    /// One tab separated line: timestamp, duration, rows, status, error, query.
+    fn to_line(&self) -> String {
+        return format!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            self.executed_at
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis(),
+            self.duration.as_micros(),
+            self.rows.map(|r| r.to_string()).unwrap_or_default(),
+            if self.is_success() { "ok" } else { "error" },
+            escape(self.error.as_deref().unwrap_or("")),
+            escape(&self.query)
+        );
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(6, '\t');
+        let executed_at = UNIX_EPOCH + Duration::from_millis(fields.next()?.parse().ok()?);
+        let duration = Duration::from_micros(fields.next()?.parse().ok()?);
+        let rows = fields.next()?.parse().ok();
+        let success = fields.next()? == "ok";
+        let error = unescape(fields.next()?);
+        let query = unescape(fields.next()?);
+        return Some(Self {
+            query,
+            executed_at,
+            duration,
+            rows,
+            error: if success { None } else { Some(error) },
+        });
+    }
+}
+
+/// Executed queries, oldest first. When `path` is set every entry is also
+/// appended to that file so the history survives restarts.
+pub struct QueryHistory {
+    pub entries: Vec<HistoryEntry>,
+    path: Option<PathBuf>,
+}
+impl QueryHistory {
+    pub fn in_memory() -> Self {
+        return Self {
+            entries: Vec::new(),
+            path: None,
+        };
+    }
+
+    /// Loads the history file belonging to `database_path` from the user's
+    /// data directory. Falls back to an in-memory history when there is none.
+    pub fn for_database(database_path: &Path) -> Self {
+        let Some(path) = data_file_path(database_path, "history") else {
+            return Self::in_memory();
+        };
+        let mut entries: Vec<HistoryEntry> = fs::read_to_string(&path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(HistoryEntry::from_line)
+            .collect();
+        if entries.len() > HISTORY_CAPACITY {
+            entries.drain(..entries.len() - HISTORY_CAPACITY);
+            // compact the file so it doesn't grow forever
+            let content: String = entries.iter().map(|e| e.to_line() + "\n").collect();
+            let _ = fs::write(&path, content);
+        }
+        return Self {
+            entries,
+            path: Some(path),
+        };
+    }
+
+    pub fn push(&mut self, entry: HistoryEntry) {
+        if let Some(path) = &self.path {
+            // history is a convenience, failing to save it shouldn't stop the query
+            let _ = append_line(path, &entry.to_line());
+        }
+        self.entries.push(entry);
+        if self.entries.len() > HISTORY_CAPACITY {
+            self.entries.remove(0);
+        }
+    }
+
+    /// Entry `position` steps back from the newest one.
+    pub fn back(&self, position: usize) -> Option<&HistoryEntry> {
+        return self.entries.iter().rev().nth(position);
+    }
+
+    /// Indices of entries containing `filter` (case insensitive), newest first.
+    pub fn search(&self, filter: &str) -> Vec<usize> {
+        let filter = filter.to_lowercase();
+        return (0..self.entries.len())
+            .rev()
+            .filter(|i| self.entries[*i].query.to_lowercase().contains(&filter))
+            .collect();
+    }
+}
+
+pub(crate) fn append_line(path: &Path, line: &str) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)?;
+    return Ok(());
+}
+
+/// `<data dir>/sqlite_viewer/<directory>/<file name>-<hash of the full path>.tsv`
+pub(crate) fn data_file_path(database_path: &Path, directory: &str) -> Option<PathBuf> {
+    let database_path = database_path
+        .canonicalize()
+        .unwrap_or(database_path.to_path_buf());
+    let name = database_path.file_name()?.to_string_lossy().to_string();
+    let directory = dirs::data_dir()?.join("sqlite_viewer").join(directory);
+    return Some(directory.join(format!(
+        "{}-{:016x}.tsv",
+        name,
+        fnv1a(database_path.to_string_lossy().as_bytes())
+    )));
+}
+
+/// Stable across builds, unlike `DefaultHasher`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    return hash;
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    return text
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r");
+}
+
+pub(crate) fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => result.push('\t'),
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    return result;
+}
+
+/// History recall and search for the query tool.
+impl SqliteDatabaseState {
+    pub(crate) fn record_history(&mut self, query: String, duration: Duration) {
+        let rows = match (&self.error, &self.queried_table_state) {
+            (Some(_), _) => None,
+            (None, Some(result)) => Some(result.rows_affected.unwrap_or(result.row_count())),
+            (None, None) => Some(0),
+        };
+        self.history.push(HistoryEntry {
+            query,
+            executed_at: SystemTime::now() - duration,
+            duration,
+            rows,
+            error: self.error.as_ref().map(|e| e.to_string()),
+        });
+        self.history_position = None;
+    }
+
+    /// Replaces the query with the previous history entry. The query being
+    /// written is kept and restored when recalling past the newest entry.
+    pub fn recall_previous(&mut self) {
+        let position = match self.history_position {
+            Some(position) => position + 1,
+            None => 0,
+        };
+        let Some(entry) = self.history.back(position) else {
+            return;
+        };
+        let query = entry.query.clone();
+        if self.history_position.is_none() {
+            self.history_draft = self.current_query.clone();
+        }
+        self.history_position = Some(position);
+        self.set_query(query);
+    }
+
+    pub fn recall_next(&mut self) {
+        match self.history_position {
+            None => (),
+            Some(0) => {
+                self.history_position = None;
+                let draft = std::mem::take(&mut self.history_draft);
+                self.set_query(draft);
+            }
+            Some(position) => {
+                if let Some(entry) = self.history.back(position - 1) {
+                    let query = entry.query.clone();
+                    self.history_position = Some(position - 1);
+                    self.set_query(query);
+                }
+            }
+        }
+    }
+
+    pub fn open_history_search(&mut self) {
+        self.history_search.clear();
+        self.history_selected = 0;
+        self.mode = SqliteDatabaseStateMode::HISTORY_SEARCH;
+    }
+
+    pub fn history_matches(&self) -> Vec<usize> {
+        return self.history.search(&self.history_search);
+    }
+
+    pub(crate) fn read_history_search_keys(&mut self, event: &KeyEvent) {
+        match event.code {
+            KeyCode::Esc => self.mode = SqliteDatabaseStateMode::QUERY_TOOL,
+            KeyCode::Enter => {
+                if let Some(index) = self.history_matches().get(self.history_selected) {
+                    let query = self.history.entries[*index].query.clone();
+                    self.history_position = None;
+                    self.set_query(query);
+                }
+                self.mode = SqliteDatabaseStateMode::QUERY_TOOL;
+            }
+            KeyCode::Up => self.history_selected = self.history_selected.saturating_sub(1),
+            KeyCode::Down => {
+                let count = self.history_matches().len();
+                self.history_selected = (self.history_selected + 1).min(count.saturating_sub(1));
+            }
+            KeyCode::Backspace => {
+                self.history_search.pop();
+                self.history_selected = 0;
+            }
+            KeyCode::Char(c)
+                if !event
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                self.history_search.push(c);
+                self.history_selected = 0;
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(query: &str) -> HistoryEntry {
+        return HistoryEntry {
+            query: query.to_string(),
+            executed_at: UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+            duration: Duration::from_micros(1500),
+            rows: Some(3),
+            error: None,
+        };
+    }
+
+    #[test]
+    fn escaping_round_trips_control_characters() {
+        let text = "a\tb\nc\r\\d\\n";
+        assert_eq!(escape(text), "a\\tb\\nc\\r\\\\d\\\\n");
+        assert!(!escape(text).contains(['\t', '\n', '\r']));
+        assert_eq!(unescape(&escape(text)), text);
+        // a trailing backslash is kept as it is
+        assert_eq!(unescape("a\\"), "a\\");
+    }
+
+    #[test]
+    fn entries_round_trip_through_a_line() {
+        let mut failed = entry("SELECT *\nFROM\t\"a\\b\"");
+        failed.rows = None;
+        failed.error = Some("no such table:\ta\\b".to_string());
+        for original in [entry("SELECT 1"), failed] {
+            let line = original.to_line();
+            assert!(!line.contains('\n'));
+            let read = HistoryEntry::from_line(&line).unwrap();
+            assert_eq!(read.query, original.query);
+            assert_eq!(read.executed_at, original.executed_at);
+            assert_eq!(read.duration, original.duration);
+            assert_eq!(read.rows, original.rows);
+            assert_eq!(read.error, original.error);
+        }
+        assert!(HistoryEntry::from_line("not a history line").is_none());
+    }
+
+    #[test]
+    fn search_is_case_insensitive_and_newest_first() {
+        let mut history = QueryHistory::in_memory();
+        for query in ["SELECT a", "select b", "DELETE FROM t"] {
+            history.push(entry(query));
+        }
+        assert_eq!(history.search("SELECT"), vec![1, 0]);
+        assert_eq!(history.back(0).unwrap().query, "DELETE FROM t");
+    }
+
+    #[test]
+    fn only_the_newest_entries_are_kept() {
+        let mut history = QueryHistory::in_memory();
+        for i in 0..HISTORY_CAPACITY + 2 {
+            history.push(entry(&i.to_string()));
+        }
+        assert_eq!(history.entries.len(), HISTORY_CAPACITY);
+        assert_eq!(history.entries[0].query, "2");
+    }
+}
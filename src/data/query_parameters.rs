@@ -0,0 +1,554 @@
+use std::{collections::HashMap, fs, path::Path, path::PathBuf};
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use rusqlite::{Statement, types::Value};
+use sqlparser::{
+    dialect::SQLiteDialect,
+    tokenizer::{Token, Tokenizer},
+};
+
+use super::{
+    query_history::{append_line, data_file_path, escape, unescape},
+    sqlite_database::{SqliteDatabaseState, SqliteDatabaseStateMode},
+};
+use crate::error::AppError;
+
+/// How the text entered for a parameter becomes its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+pub enum ParameterKind {
+    NULL,
+    INTEGER,
+    REAL,
+    TEXT,
+    /// Hex digits, e.g. `DEADBEEF`.
+    BLOB_HEX,
+    /// The contents of the file at the entered path.
+    BLOB_FILE,
+}
+impl ParameterKind {
+    const ALL: [ParameterKind; 6] = [
+        ParameterKind::NULL,
+        ParameterKind::INTEGER,
+        ParameterKind::REAL,
+        ParameterKind::TEXT,
+        ParameterKind::BLOB_HEX,
+        ParameterKind::BLOB_FILE,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        return match self {
+            ParameterKind::NULL => "NULL",
+            ParameterKind::INTEGER => "INTEGER",
+            ParameterKind::REAL => "REAL",
+            ParameterKind::TEXT => "TEXT",
+            ParameterKind::BLOB_HEX => "BLOB (hex)",
+            ParameterKind::BLOB_FILE => "BLOB (file)",
+        };
+    }
+
+    fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|k| k == self).unwrap_or(0);
+        return Self::ALL[(index + 1) % Self::ALL.len()];
+    }
+
+    fn from_label(label: &str) -> Option<Self> {
+        return Self::ALL.into_iter().find(|k| k.label() == label);
+    }
+}
+
+/// One parameter in the dialog.
+#[derive(Debug, Clone)]
+pub struct ParameterField {
+    /// As SQLite names it, e.g. `:name`, `@name` or `?2`.
+    pub name: String,
+    pub kind: ParameterKind,
+    pub text: String,
+}
+impl ParameterField {
+    pub fn value(&self) -> Result<Value, AppError> {
+        let invalid = |what: &str| {
+            return AppError::UI(format!("{}: {:?} is not {}", self.name, self.text, what));
+        };
+        return match self.kind {
+            ParameterKind::NULL => Ok(Value::Null),
+            ParameterKind::INTEGER => self
+                .text
+                .trim()
+                .parse()
+                .map(Value::Integer)
+                .map_err(|_| invalid("an integer")),
+            ParameterKind::REAL => self
+                .text
+                .trim()
+                .parse()
+                .map(Value::Real)
+                .map_err(|_| invalid("a number")),
+            ParameterKind::TEXT => Ok(Value::Text(self.text.clone())),
+            ParameterKind::BLOB_HEX => parse_hex(&self.text)
+                .map(Value::Blob)
+                .ok_or_else(|| invalid("hex")),
+            ParameterKind::BLOB_FILE => Ok(Value::Blob(fs::read(self.text.trim())?)),
+        };
+    }
+}
+
+/// `DEADBEEF`, also with spaces between bytes or written as `x'DEADBEEF'`.
+fn parse_hex(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim();
+    let text = text
+        .strip_prefix("x'")
+        .or_else(|| text.strip_prefix("X'"))
+        .and_then(|t| t.strip_suffix('\''))
+        .unwrap_or(text);
+    let digits: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    return digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(&pair.iter().collect::<String>(), 16).ok())
+        .collect();
+}
+
+/// How SQLite names parameter `index`, `?N` for one written as a bare `?`.
+pub fn parameter_name(stmt: &Statement, index: usize) -> String {
+    return stmt
+        .parameter_name(index)
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| format!("?{}", index));
+}
+
+/// The values of `stmt`'s parameters in position order, looked up by name.
+/// A parameter without a value is an error, it isn't bound to NULL.
+pub fn statement_values(
+    stmt: &Statement,
+    values: &[(String, Value)],
+) -> Result<Vec<Value>, AppError> {
+    return (1..=stmt.parameter_count())
+        .map(|index| {
+            let name = parameter_name(stmt, index);
+            return values
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, value)| value.clone())
+                .ok_or_else(|| AppError::UI(format!("no value was given for {}", name)));
+        })
+        .collect();
+}
+
+/// Parameter names of `statements` in position order, a name used in
+/// several statements only once. They are read from the tokens so
+/// statements that only prepare once the ones before them ran, e.g. an
+/// INSERT into a table the script creates, are covered too. Positions are
+/// handed out like SQLite does, unnamed ones are called `?N` as in
+/// `parameter_name`.
+pub fn parameter_names(statements: &[String]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for sql in statements {
+        let Ok(tokens) = Tokenizer::new(&SQLiteDialect {}, sql).tokenize() else {
+            // running it reports the error
+            continue;
+        };
+        // by position, `None` for `?` and the gaps `?N` leaves
+        let mut positions: Vec<Option<String>> = Vec::new();
+        let mut tokens = tokens.iter().peekable();
+        while let Some(token) = tokens.next() {
+            let name = match (token, tokens.peek()) {
+                (Token::Placeholder(p), _) if p == "?" => {
+                    positions.push(None);
+                    continue;
+                }
+                (Token::Placeholder(p), _) => match p.strip_prefix('?') {
+                    Some(number) => {
+                        if let Ok(index) = number.parse::<usize>()
+                            && index > positions.len()
+                        {
+                            positions.resize(index, None);
+                        }
+                        continue;
+                    }
+                    None => p.clone(),
+                },
+                // `:name` and `@name` come out of the tokenizer in two parts
+                (Token::Colon | Token::AtSign, Some(Token::Word(w))) if w.quote_style.is_none() => {
+                    let name = format!("{}{}", token, w.value);
+                    tokens.next();
+                    name
+                }
+                (Token::Colon | Token::AtSign, Some(Token::Number(n, _))) => {
+                    let name = format!("{}{}", token, n);
+                    tokens.next();
+                    name
+                }
+                _ => continue,
+            };
+            if !positions.contains(&Some(name.clone())) {
+                positions.push(Some(name));
+            }
+        }
+        for (index, name) in positions.into_iter().enumerate() {
+            let name = name.unwrap_or_else(|| format!("?{}", index + 1));
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    return names;
+}
+
+/// Asks for the values of a query's parameters before running it.
+pub struct ParameterDialog {
+    pub query: String,
+    pub fields: Vec<ParameterField>,
+    pub focus: usize,
+}
+
+/// The values last used for each query, so running it again or recalling it
+/// from the history only needs Enter. Like the history it is kept in a file
+/// per database, the last line for a query wins.
+pub struct ParameterMemory {
+    values: HashMap<String, Vec<ParameterField>>,
+    path: Option<PathBuf>,
+}
+impl ParameterMemory {
+    pub fn in_memory() -> Self {
+        return Self {
+            values: HashMap::new(),
+            path: None,
+        };
+    }
+
+    pub fn for_database(database_path: &Path) -> Self {
+        let Some(path) = data_file_path(database_path, "parameters") else {
+            return Self::in_memory();
+        };
+        let content = fs::read_to_string(&path).unwrap_or_default();
+        let mut values = HashMap::new();
+        for line in content.lines() {
+            if let Some((query, fields)) = from_line(line) {
+                values.insert(query, fields);
+            }
+        }
+        let memory = Self {
+            values,
+            path: Some(path),
+        };
+        if content.lines().count() > memory.values.len() * 2 {
+            // compact the file so it doesn't grow forever
+            if let Some(path) = &memory.path {
+                let content: String = memory
+                    .values
+                    .iter()
+                    .map(|(query, fields)| to_line(query, fields) + "\n")
+                    .collect();
+                let _ = fs::write(path, content);
+            }
+        }
+        return memory;
+    }
+
+    pub fn get(&self, query: &str) -> Option<&Vec<ParameterField>> {
+        return self.values.get(query);
+    }
+
+    pub fn remember(&mut self, query: &str, fields: &[ParameterField]) {
+        if let Some(path) = &self.path {
+            // like the history, failing to save shouldn't stop the query
+            let _ = append_line(path, &to_line(query, fields));
+        }
+        self.values.insert(query.to_string(), fields.to_vec());
+    }
+}
+
+/// Tab separated: the query, then name, kind and text of every parameter.
+fn to_line(query: &str, fields: &[ParameterField]) -> String {
+    let mut line = escape(query);
+    for field in fields {
+        line.push_str(&format!(
+            "\t{}\t{}\t{}",
+            escape(&field.name),
+            field.kind.label(),
+            escape(&field.text)
+        ));
+    }
+    return line;
+}
+
+fn from_line(line: &str) -> Option<(String, Vec<ParameterField>)> {
+    let mut parts = line.split('\t');
+    let query = unescape(parts.next()?);
+    let parts: Vec<&str> = parts.collect();
+    let fields = parts
+        .chunks(3)
+        .map(|field| {
+            let [name, kind, text] = field else {
+                return None;
+            };
+            return Some(ParameterField {
+                name: unescape(name),
+                kind: ParameterKind::from_label(kind)?,
+                text: unescape(text),
+            });
+        })
+        .collect::<Option<_>>()?;
+    return Some((query, fields));
+}
+
+/// Queries with parameters ask for their values first.
+impl SqliteDatabaseState {
+    /// Opens the dialog for `names`, filled with the values last used for
+    /// `current_query`.
+    pub(crate) fn open_parameter_dialog(&mut self, names: Vec<String>) {
+        let remembered = self.parameter_memory.get(&self.current_query);
+        let fields = names
+            .into_iter()
+            .map(|name| {
+                return remembered
+                    .and_then(|fields| fields.iter().find(|f| f.name == name))
+                    .cloned()
+                    .unwrap_or(ParameterField {
+                        name,
+                        kind: ParameterKind::TEXT,
+                        text: String::new(),
+                    });
+            })
+            .collect();
+        self.parameter_dialog = Some(ParameterDialog {
+            query: self.current_query.clone(),
+            fields,
+            focus: 0,
+        });
+        self.error = None;
+        self.mode = SqliteDatabaseStateMode::PARAMETERS;
+    }
+
+    /// Reads the values, remembers them and runs the query.
+    fn confirm_parameters(&mut self) {
+        let Some(dialog) = &self.parameter_dialog else {
+            return;
+        };
+        let values = dialog
+            .fields
+            .iter()
+            .map(|field| Ok((field.name.clone(), field.value()?)))
+            .collect::<Result<Vec<_>, AppError>>();
+        let values = match values {
+            Ok(values) => values,
+            Err(e) => {
+                self.error = Some(e);
+                return;
+            }
+        };
+        self.parameter_memory
+            .remember(&dialog.query, &dialog.fields);
+        self.parameter_dialog = None;
+        self.mode = SqliteDatabaseStateMode::QUERY_TOOL;
+        self.run_query(values);
+    }
+
+    fn close_parameter_dialog(&mut self) {
+        self.parameter_dialog = None;
+        self.error = None;
+        self.mode = SqliteDatabaseStateMode::QUERY_TOOL;
+    }
+
+    pub(crate) fn read_parameter_keys(&mut self, event: &KeyEvent) {
+        let Some(dialog) = &mut self.parameter_dialog else {
+            self.mode = SqliteDatabaseStateMode::QUERY_TOOL;
+            return;
+        };
+        let control = event.modifiers.contains(KeyModifiers::CONTROL);
+        let count = dialog.fields.len();
+        let field = dialog.fields.get_mut(dialog.focus);
+        match event.code {
+            KeyCode::Esc => self.close_parameter_dialog(),
+            KeyCode::Enter => self.confirm_parameters(),
+            KeyCode::Down | KeyCode::Tab if count > 0 => dialog.focus = (dialog.focus + 1) % count,
+            KeyCode::Up | KeyCode::BackTab if count > 0 => {
+                dialog.focus = (dialog.focus + count - 1) % count
+            }
+            KeyCode::Char('t') if control => {
+                if let Some(field) = field {
+                    field.kind = field.kind.next();
+                }
+            }
+            KeyCode::Char('n') if control => {
+                if let Some(field) = field {
+                    field.kind = ParameterKind::NULL;
+                }
+            }
+            KeyCode::Backspace => {
+                if let Some(field) = field {
+                    field.text.pop();
+                }
+            }
+            KeyCode::Char(c)
+                if !event
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                if let Some(field) = field {
+                    if field.kind == ParameterKind::NULL {
+                        field.kind = ParameterKind::TEXT;
+                    }
+                    field.text.push(c);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use super::*;
+
+    fn field(name: &str, kind: ParameterKind, text: &str) -> ParameterField {
+        return ParameterField {
+            name: name.to_string(),
+            kind,
+            text: text.to_string(),
+        };
+    }
+
+    #[test]
+    fn hex_is_read_plain_quoted_or_spaced() {
+        assert_eq!(parse_hex("DEADbeef"), Some(vec![0xde, 0xad, 0xbe, 0xef]));
+        assert_eq!(parse_hex(" x'00ff' "), Some(vec![0x00, 0xff]));
+        assert_eq!(parse_hex("X'0A'"), Some(vec![0x0a]));
+        assert_eq!(
+            parse_hex("de ad\nbe\tef"),
+            Some(vec![0xde, 0xad, 0xbe, 0xef])
+        );
+        assert_eq!(parse_hex(""), Some(vec![]));
+        assert_eq!(parse_hex("abc"), None);
+        assert_eq!(parse_hex("x'abc'"), None);
+        assert_eq!(parse_hex("zz"), None);
+    }
+
+    #[test]
+    fn values_are_checked_against_the_kind() {
+        assert_eq!(
+            field(":a", ParameterKind::INTEGER, " 42 ").value().unwrap(),
+            Value::Integer(42)
+        );
+        assert!(field(":a", ParameterKind::INTEGER, "4.2").value().is_err());
+        assert_eq!(
+            field(":a", ParameterKind::REAL, "4.5").value().unwrap(),
+            Value::Real(4.5)
+        );
+        assert_eq!(
+            field(":a", ParameterKind::NULL, "ignored").value().unwrap(),
+            Value::Null
+        );
+        assert_eq!(
+            field(":a", ParameterKind::BLOB_HEX, "x'01'")
+                .value()
+                .unwrap(),
+            Value::Blob(vec![1])
+        );
+    }
+
+    #[test]
+    fn memory_lines_keep_tabs_and_newlines() {
+        let query = "SELECT *\nFROM t\tWHERE a = :a AND b = '\\t'";
+        let fields = vec![
+            field(":a", ParameterKind::TEXT, "two\tcolumns\nand a line"),
+            field("?2", ParameterKind::BLOB_HEX, "de ad"),
+            field("@c", ParameterKind::NULL, ""),
+        ];
+        let line = to_line(query, &fields);
+        assert!(!line.contains('\n'));
+        assert_eq!(line.split('\t').count(), 1 + 3 * fields.len());
+        let (read_query, read_fields) = from_line(&line).unwrap();
+        assert_eq!(read_query, query);
+        assert_eq!(read_fields.len(), fields.len());
+        for (read, field) in read_fields.iter().zip(&fields) {
+            assert_eq!(read.name, field.name);
+            assert_eq!(read.kind, field.kind);
+            assert_eq!(read.text, field.text);
+        }
+        assert!(from_line("SELECT :a\t:a\tNOT A KIND\tx").is_none());
+        assert!(from_line("SELECT :a\t:a\tTEXT").is_none());
+    }
+
+    #[test]
+    fn values_are_bound_by_name() {
+        let connection = Connection::open_in_memory().unwrap();
+        let stmt = connection.prepare("SELECT ?, :a, ?5, @b").unwrap();
+        let names: Vec<String> = (1..=stmt.parameter_count())
+            .map(|index| parameter_name(&stmt, index))
+            .collect();
+        assert_eq!(names, vec!["?1", ":a", "?3", "?4", "?5", "@b"]);
+        let mut values = vec![
+            ("@b".to_string(), Value::Integer(4)),
+            ("?1".to_string(), Value::Integer(1)),
+            (":a".to_string(), Value::Text("a".to_string())),
+            ("?5".to_string(), Value::Integer(5)),
+        ];
+        assert_eq!(
+            statement_values(&stmt, &values).unwrap_err().to_string(),
+            "no value was given for ?3"
+        );
+        values.push(("?3".to_string(), Value::Null));
+        values.push(("?4".to_string(), Value::Integer(0)));
+        assert_eq!(
+            statement_values(&stmt, &values).unwrap(),
+            vec![
+                Value::Integer(1),
+                Value::Text("a".to_string()),
+                Value::Null,
+                Value::Integer(0),
+                Value::Integer(5),
+                Value::Integer(4)
+            ]
+        );
+    }
+
+    fn names(statements: &[&str]) -> Vec<String> {
+        let statements: Vec<String> = statements.iter().map(|s| s.to_string()).collect();
+        return parameter_names(&statements);
+    }
+
+    #[test]
+    fn names_match_the_ones_sqlite_gives() {
+        let connection = Connection::open_in_memory().unwrap();
+        for sql in [
+            "SELECT ?, ?3, :a, @b, $c, ?",
+            "SELECT :a, ?, :a, ?2, @b",
+            "SELECT ?2, :x, ?",
+            "SELECT $c, :1, @2",
+        ] {
+            let stmt = connection.prepare(sql).unwrap();
+            let expected: Vec<String> = (1..=stmt.parameter_count())
+                .map(|index| parameter_name(&stmt, index))
+                .collect();
+            assert_eq!(names(&[sql]), expected, "{}", sql);
+        }
+        assert_eq!(
+            names(&["SELECT ?, ?3, :a, @b, $c"]),
+            vec!["?1", "?2", "?3", ":a", "@b", "$c"]
+        );
+    }
+
+    #[test]
+    fn names_are_shared_across_statements() {
+        assert_eq!(
+            names(&[
+                "CREATE TABLE t (a, b)",
+                "INSERT INTO t VALUES (:a, ?)",
+                "SELECT * FROM t WHERE a = :a OR b = ?2 OR b = @b",
+            ]),
+            vec![":a", "?2", "@b"]
+        );
+    }
+
+    #[test]
+    fn text_that_looks_like_a_parameter_is_not_one() {
+        assert!(names(&["SELECT ':a', \"@b\", '?' -- ?\n/* :c */"]).is_empty());
+        assert!(names(&["SELECT 'unterminated :a"]).is_empty());
+    }
+}
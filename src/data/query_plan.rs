@@ -0,0 +1,177 @@
+use std::cell::Cell;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use rusqlite::{Connection, Error, Statement, params_from_iter, types::Value};
+
+use super::{
+    query_editor::PAGE_LINES,
+    query_parameters::parameter_name,
+    sqlite_database::{SqliteDatabaseState, SqliteDatabaseStateMode},
+};
+use crate::error::AppError;
+
+/// A row of `EXPLAIN QUERY PLAN`.
+#[derive(Debug)]
+pub struct PlanNode {
+    pub id: i64,
+    /// `0` for top level steps.
+    pub parent: i64,
+    pub detail: String,
+}
+
+/// A row of `EXPLAIN`, one virtual machine instruction.
+#[derive(Debug)]
+pub struct BytecodeRow {
+    pub address: i64,
+    pub opcode: String,
+    pub p1: i64,
+    pub p2: i64,
+    pub p3: i64,
+    pub p4: String,
+    pub p5: i64,
+    pub comment: String,
+}
+
+/// The plan of `current_query`, read when the view opens. The bytecode is
+/// only read once asked for.
+pub struct QueryPlan {
+    pub sql: String,
+    /// Values for the query's parameters by name, see `plan_values`.
+    parameters: Vec<(String, Value)>,
+    pub nodes: Vec<PlanNode>,
+    pub bytecode: Option<Vec<BytecodeRow>>,
+    pub show_bytecode: bool,
+    pub scroll: usize,
+    /// Furthest the view can scroll, written on every render.
+    pub max_scroll: Cell<usize>,
+}
+impl QueryPlan {
+    pub fn explain(
+        connection: &Connection,
+        sql: &str,
+        parameters: Vec<(String, Value)>,
+    ) -> Result<Self, Error> {
+        let mut stmt = connection.prepare(&format!("EXPLAIN QUERY PLAN {}", sql))?;
+        let values = plan_values(&stmt, &parameters);
+        let nodes = stmt
+            .query_map(params_from_iter(&values), |row| {
+                return Ok(PlanNode {
+                    id: row.get(0)?,
+                    parent: row.get(1)?,
+                    detail: row.get(3)?,
+                });
+            })?
+            .collect::<Result<_, _>>()?;
+        return Ok(Self {
+            sql: sql.to_string(),
+            parameters,
+            nodes,
+            bytecode: None,
+            show_bytecode: false,
+            scroll: 0,
+            max_scroll: Cell::new(0),
+        });
+    }
+
+    fn load_bytecode(&mut self, connection: &Connection) -> Result<(), Error> {
+        if self.bytecode.is_some() {
+            return Ok(());
+        }
+        let mut stmt = connection.prepare(&format!("EXPLAIN {}", self.sql))?;
+        let values = plan_values(&stmt, &self.parameters);
+        let rows = stmt
+            .query_map(params_from_iter(&values), |row| {
+                return Ok(BytecodeRow {
+                    address: row.get(0)?,
+                    opcode: row.get(1)?,
+                    p1: row.get(2)?,
+                    p2: row.get(3)?,
+                    p3: row.get(4)?,
+                    p4: row.get::<_, Option<String>>(5)?.unwrap_or_default(),
+                    p5: row.get(6)?,
+                    comment: row.get::<_, Option<String>>(7)?.unwrap_or_default(),
+                });
+            })?
+            .collect::<Result<_, _>>()?;
+        self.bytecode = Some(rows);
+        return Ok(());
+    }
+
+    /// Steps whose parent is `parent`, in plan order.
+    pub fn children(&self, parent: i64) -> impl Iterator<Item = &PlanNode> {
+        return self.nodes.iter().filter(move |n| n.parent == parent);
+    }
+}
+
+/// Values for `stmt`'s parameters by name. The statement is only explained,
+/// not run, so NULL stands in for values that were never entered.
+fn plan_values(stmt: &Statement, parameters: &[(String, Value)]) -> Vec<Value> {
+    return (1..=stmt.parameter_count())
+        .map(|index| {
+            let name = parameter_name(stmt, index);
+            return parameters
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map_or(Value::Null, |(_, value)| value.clone());
+        })
+        .collect();
+}
+
+/// Showing the plan of the query being written.
+impl SqliteDatabaseState {
+    /// Explains `current_query` without running it. `EXPLAIN` never writes, so
+    /// this works for any statement, also on a read-only database. Parameters
+    /// get the values last entered for the query.
+    pub fn open_query_plan(&mut self) {
+        if self.current_query.trim().is_empty() {
+            return;
+        }
+        let parameters = self
+            .parameter_memory
+            .get(&self.current_query)
+            .map(|fields| {
+                return fields
+                    .iter()
+                    .filter_map(|field| Some((field.name.clone(), field.value().ok()?)))
+                    .collect();
+            })
+            .unwrap_or_default();
+        match QueryPlan::explain(&self.database.connection, &self.current_query, parameters) {
+            Ok(plan) => {
+                self.error = None;
+                self.query_plan = Some(plan);
+                self.mode = SqliteDatabaseStateMode::QUERY_PLAN;
+            }
+            Err(e) => self.error = Some(e.into()),
+        }
+    }
+
+    pub(crate) fn read_query_plan_keys(&mut self, event: &KeyEvent) {
+        let Some(plan) = &mut self.query_plan else {
+            self.mode = SqliteDatabaseStateMode::QUERY_TOOL;
+            return;
+        };
+        let max_scroll = plan.max_scroll.get();
+        match event.code {
+            KeyCode::Esc | KeyCode::F(4) => {
+                self.query_plan = None;
+                self.mode = SqliteDatabaseStateMode::QUERY_TOOL;
+            }
+            KeyCode::Char('b') => {
+                if let Err(e) = plan.load_bytecode(&self.database.connection) {
+                    self.error = Some(AppError::from(e));
+                    return;
+                }
+                plan.show_bytecode = !plan.show_bytecode;
+                plan.scroll = 0;
+            }
+            KeyCode::Up => plan.scroll = plan.scroll.saturating_sub(1),
+            KeyCode::Down => plan.scroll = (plan.scroll + 1).min(max_scroll),
+            KeyCode::PageUp => plan.scroll = plan.scroll.saturating_sub(PAGE_LINES),
+            KeyCode::PageDown => plan.scroll = (plan.scroll + PAGE_LINES).min(max_scroll),
+            KeyCode::Home => plan.scroll = 0,
+            KeyCode::End => plan.scroll = max_scroll,
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,446 @@
+use std::{
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use rusqlite::{Connection, InterruptHandle, params_from_iter, types::Value};
+use sqlparser::{
+    dialect::SQLiteDialect,
+    keywords::Keyword,
+    tokenizer::{Token, Tokenizer},
+};
+
+use super::{
+    cell_editor::with_rowid_key,
+    query_parameters::{parameter_names, statement_values},
+    script::{ScriptOptions, ScriptReport, run_script, split_statements},
+    sqlite_database::{
+        SqliteDatabase, SqliteDatabaseState, SqliteDatabaseStateMode, pending_edits_error,
+        read_only_error,
+    },
+    sqlite_query::{QueryStats, RowCount, SqliteQueryResult, StatementCounters},
+    transaction::controls_transaction,
+};
+use crate::{crash, error::AppError};
+
+struct QueryJob {
+    sql: String,
+    /// `sql` split up, with more than one statement it runs as a script.
+    statements: Vec<String>,
+    options: ScriptOptions,
+    /// Parameter values by name, see `statement_values`.
+    parameters: Vec<(String, Value)>,
+    readonly: bool,
+}
+impl QueryJob {
+    fn run(self, connection: &Connection) -> QueryOutcome {
+        let start = Instant::now();
+        if self.statements.len() <= 1 {
+            let result = run_statement(connection, &self.sql, &self.parameters, self.readonly);
+            return QueryOutcome {
+                sql: self.sql,
+                result,
+                script: None,
+                duration: start.elapsed(),
+            };
+        }
+        let (report, result) = run_script(
+            connection,
+            &self.statements,
+            self.options,
+            &self.parameters,
+            self.readonly,
+        );
+        return QueryOutcome {
+            sql: self.sql,
+            result,
+            script: Some(report),
+            duration: start.elapsed(),
+        };
+    }
+}
+
+/// Work for the worker: a query, or counting the rows of a result for
+/// jumping to its end.
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+enum WorkerJob {
+    QUERY(QueryJob),
+    COUNT(RowCount),
+}
+
+/// What the worker sends back, one per job.
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+enum WorkerOutcome {
+    QUERY(Box<QueryOutcome>),
+    COUNT(RowCount, Result<usize, AppError>),
+}
+
+/// A finished query, sent back by the worker. For a script `result` is the
+/// result of the last statement that ran.
+pub struct QueryOutcome {
+    pub sql: String,
+    pub result: Result<SqliteQueryResult, AppError>,
+    pub script: Option<ScriptReport>,
+    pub duration: Duration,
+}
+
+/// The job the worker is busy with, for the spinner.
+pub struct RunningQuery {
+    pub started: Instant,
+    /// What is running, e.g. "Running query".
+    pub activity: &'static str,
+}
+
+/// Runs queries on a thread with its own connection so a slow query doesn't
+/// block the UI. Only the first page is fetched there, paging through the
+/// result afterwards uses the UI's connection. That works because the worker
+/// never gets statements that leave state on its connection, see
+/// `changes_connection_state`.
+pub struct QueryWorker {
+    jobs: Sender<WorkerJob>,
+    outcomes: Receiver<WorkerOutcome>,
+    interrupt: InterruptHandle,
+}
+impl QueryWorker {
+    /// Opens a second connection to `database` and starts the worker on it.
+    /// `notify` is called whenever a query finished.
+    pub fn start(
+        database: &SqliteDatabase,
+        notify: impl Fn() + Send + 'static,
+    ) -> Result<Self, AppError> {
+        let connection = database.open_connection()?;
+        let interrupt = connection.get_interrupt_handle();
+        let (jobs, job_receiver) = mpsc::channel::<WorkerJob>();
+        let (outcome_sender, outcomes) = mpsc::channel();
+        thread::spawn(move || {
+            for job in job_receiver {
+                let outcome = match job {
+                    WorkerJob::QUERY(job) => WorkerOutcome::QUERY(Box::new(job.run(&connection))),
+                    WorkerJob::COUNT(count) => {
+                        let total = count.run(&connection).map_err(AppError::from);
+                        WorkerOutcome::COUNT(count, total)
+                    }
+                };
+                if outcome_sender.send(outcome).is_err() {
+                    return;
+                }
+                notify();
+            }
+        });
+        return Ok(Self {
+            jobs,
+            outcomes,
+            interrupt,
+        });
+    }
+
+    /// Gives the job back when the worker is gone.
+    fn submit(&self, job: WorkerJob) -> Result<(), WorkerJob> {
+        return self.jobs.send(job).map_err(|e| e.0);
+    }
+
+    /// Stops the running job, it then finishes with `AppError::INTERRUPTED`.
+    fn interrupt(&self) {
+        self.interrupt.interrupt();
+    }
+}
+
+/// Runs one statement with `parameters` bound: read-only ones give their
+/// first page of rows, with a hidden rowid when `with_rowid_key` adds one,
+/// the others the number of rows they changed. Statements
+/// that write are refused when `readonly` is set.
+pub fn run_statement(
+    connection: &Connection,
+    sql: &str,
+    parameters: &[(String, Value)],
+    readonly: bool,
+) -> Result<SqliteQueryResult, AppError> {
+    let mut stmt = connection.prepare(sql)?;
+    let values = statement_values(&stmt, parameters)?;
+    if !stmt.readonly() {
+        if readonly {
+            return Err(read_only_error());
+        }
+        let start = Instant::now();
+        let rowid_before = connection.last_insert_rowid();
+        let total_before = connection.total_changes();
+        let mut rows_changed = stmt.execute(params_from_iter(&values))?;
+        // statements like CREATE leave the count of the last INSERT/UPDATE/DELETE
+        if connection.total_changes() == total_before {
+            rows_changed = 0;
+        }
+        let rowid = connection.last_insert_rowid();
+        let stats = QueryStats {
+            duration: start.elapsed(),
+            counters: StatementCounters::read(&stmt),
+            last_insert_rowid: (rowid != rowid_before).then_some(rowid),
+        };
+        return Ok(SqliteQueryResult::mutated(rows_changed, stats));
+    }
+    drop(stmt);
+    let (sql, hidden_columns) = match with_rowid_key(connection, sql) {
+        Some(keyed) => (keyed, 1),
+        None => (sql.to_string(), 0),
+    };
+    let column_names = connection
+        .prepare(&sql)?
+        .column_names()
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let mut result = SqliteQueryResult::query(connection, &sql, column_names, values)?;
+    result.hidden_columns = hidden_columns;
+    return Ok(result);
+}
+
+/// Whether one of `statements` changes what SQLite keeps per connection:
+/// attached databases, temporary tables, views and triggers, and pragma
+/// settings. Paging, editing and the schema tree use the UI's connection, so
+/// these have to run there.
+pub fn changes_connection_state(statements: &[String]) -> bool {
+    return statements.iter().any(|sql| {
+        let tokens: Vec<Token> = Tokenizer::new(&SQLiteDialect {}, sql)
+            .tokenize()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|t| !matches!(t, Token::Whitespace(_)))
+            .collect();
+        let keyword = |token: &Token| match token {
+            Token::Word(w) => w.keyword,
+            _ => Keyword::NoKeyword,
+        };
+        return match tokens.first().map(keyword) {
+            Some(Keyword::ATTACH | Keyword::DETACH) => true,
+            // `PRAGMA name = value` sets, the other forms mostly read
+            Some(Keyword::PRAGMA) => tokens.contains(&Token::Eq),
+            // `CREATE TEMP TABLE t` or `CREATE TABLE temp.t`, before the body
+            Some(Keyword::CREATE) => tokens
+                .iter()
+                .take_while(|t| {
+                    **t != Token::LParen && !matches!(keyword(t), Keyword::AS | Keyword::BEGIN)
+                })
+                .any(|t| matches!(keyword(t), Keyword::TEMP | Keyword::TEMPORARY)),
+            _ => false,
+        };
+    });
+}
+
+/// Running queries, in the background when a worker was started and on the
+/// UI's connection otherwise (e.g. with `--query`).
+impl SqliteDatabaseState {
+    /// Starts the background worker. Without one queries keep running on the
+    /// UI thread.
+    pub fn start_worker(&mut self, notify: impl Fn() + Send + 'static) {
+        match QueryWorker::start(&self.database, notify) {
+            Ok(worker) => self.worker = Some(worker),
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    /// Runs `current_query` and records it in the history. When it has
+    /// parameters their values are asked for first.
+    pub fn execute(&mut self) {
+        if !self.pending_edits.is_empty() {
+            self.edit_error = Some(pending_edits_error());
+            self.mode = SqliteDatabaseStateMode::RESULT_NAVIGATION;
+            return;
+        }
+        if self.running_query.is_some() {
+            return;
+        }
+        let names = parameter_names(&split_statements(&self.current_query));
+        if !names.is_empty() {
+            self.open_parameter_dialog(names);
+            return;
+        }
+        self.run_query(Vec::new());
+    }
+
+    /// Runs `current_query` with `parameters` bound. Several statements run
+    /// as a script with `script_options`. While a transaction is open, and
+    /// for statements that open or close one, the UI's connection is used so
+    /// the transaction stays in one place; those can't be cancelled. The same
+    /// goes for everything after a statement that changed the connection's
+    /// state, like `ATTACH`.
+    pub(crate) fn run_query(&mut self, parameters: Vec<(String, Value)>) {
+        if self.running_query.is_some() {
+            return;
+        }
+        crash::record_query(&self.current_query);
+        let mut job = QueryJob {
+            statements: split_statements(&self.current_query),
+            sql: self.current_query.clone(),
+            options: self.script_options,
+            parameters,
+            readonly: self.database.readonly,
+        };
+        self.connection_state_changed |= changes_connection_state(&job.statements);
+        let on_ui_connection = self.connection_state_changed
+            || self.in_transaction()
+            || controls_transaction(&job.statements);
+        if let Some(worker) = &self.worker
+            && !on_ui_connection
+        {
+            match worker.submit(WorkerJob::QUERY(job)) {
+                Ok(()) => {
+                    self.running_query = Some(RunningQuery {
+                        started: Instant::now(),
+                        activity: "Running query",
+                    });
+                    return;
+                }
+                Err(WorkerJob::QUERY(returned)) => job = returned,
+                Err(WorkerJob::COUNT(_)) => unreachable!("a query was submitted"),
+            }
+        }
+        let outcome = job.run(&self.database.connection);
+        self.finish_query(outcome);
+    }
+
+    /// Counts the rows of the current result so the cursor can jump to the
+    /// end. It runs on the worker under the same conditions as `run_query`,
+    /// the jump follows once the total is back.
+    pub(crate) fn count_result_rows(&mut self) {
+        if self.running_query.is_some() {
+            return;
+        }
+        let Some(result) = &self.queried_table_state else {
+            return;
+        };
+        let mut count = result.counting();
+        if let Some(worker) = &self.worker
+            && !self.connection_state_changed
+            && !self.in_transaction()
+        {
+            match worker.submit(WorkerJob::COUNT(count)) {
+                Ok(()) => {
+                    self.running_query = Some(RunningQuery {
+                        started: Instant::now(),
+                        activity: "Counting rows",
+                    });
+                    return;
+                }
+                Err(WorkerJob::COUNT(returned)) => count = returned,
+                Err(WorkerJob::QUERY(_)) => unreachable!("a count was submitted"),
+            }
+        }
+        let total = count.run(&self.database.connection).map_err(AppError::from);
+        self.finish_row_count(count, total);
+    }
+
+    /// Takes the worker's result once the running job finished.
+    pub fn poll_query(&mut self) {
+        let Some(worker) = &self.worker else {
+            return;
+        };
+        if let Ok(outcome) = worker.outcomes.try_recv() {
+            self.running_query = None;
+            match outcome {
+                WorkerOutcome::QUERY(outcome) => self.finish_query(*outcome),
+                WorkerOutcome::COUNT(count, total) => self.finish_row_count(count, total),
+            }
+        }
+    }
+
+    /// A cancelled count leaves the result as it was.
+    fn finish_row_count(&mut self, count: RowCount, total: Result<usize, AppError>) {
+        match total {
+            Ok(total) => {
+                if let Some(result) = &mut self.queried_table_state
+                    && result.set_total_rows(&count, total)
+                {
+                    self.move_result_cursor_to(Some(usize::MAX), None);
+                }
+            }
+            Err(AppError::INTERRUPTED) => (),
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    pub fn cancel_query(&mut self) {
+        if let (Some(worker), Some(_)) = (&self.worker, &self.running_query) {
+            worker.interrupt();
+        }
+    }
+
+    fn finish_query(&mut self, outcome: QueryOutcome) {
+        self.error = None;
+        self.edit_target = None;
+        self.edit_error = None;
+        self.marked_rows.clear();
+        self.reset_result_navigation();
+        // the new result is current, whatever changed before it
+        self.external_change = None;
+        // statements of a script before the last one may have written
+        let wrote = outcome.script.is_some()
+            || matches!(&outcome.result, Ok(result) if !result.is_readonly());
+        if wrote {
+            self.acknowledge_own_changes();
+        }
+        self.end_transaction_if_closed();
+        self.script_report = outcome.script;
+        self.export_notice = None;
+        match outcome.result {
+            Ok(result) => self.queried_table_state = Some(result),
+            Err(e) => self.error = Some(e),
+        }
+        self.record_history(outcome.sql, outcome.duration);
+    }
+
+    /// Esc and Ctrl+C cancel the running job, returns false for other keys
+    /// and when nothing is running.
+    pub(crate) fn read_running_query_keys(&mut self, event: &KeyEvent) -> bool {
+        if self.running_query.is_none() {
+            return false;
+        }
+        let control = event.modifiers.contains(KeyModifiers::CONTROL);
+        match event.code {
+            KeyCode::Esc => self.cancel_query(),
+            KeyCode::Char('c') if control => self.cancel_query(),
+            _ => return false,
+        }
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes_state(sql: &str) -> bool {
+        return changes_connection_state(&split_statements(sql));
+    }
+
+    #[test]
+    fn finds_statements_that_change_the_connection() {
+        for sql in [
+            "ATTACH 'other.db' AS other",
+            "detach other",
+            "PRAGMA foreign_keys = ON",
+            "CREATE TEMP TABLE t (a)",
+            "CREATE TEMPORARY VIEW v AS SELECT 1",
+            "CREATE TABLE temp.t (a)",
+            "CREATE TEMP TRIGGER x AFTER INSERT ON t BEGIN SELECT 1; END",
+            "SELECT 1; PRAGMA cache_size = 100",
+        ] {
+            assert!(changes_state(sql), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn leaves_other_statements_on_the_worker() {
+        for sql in [
+            "SELECT 'ATTACH'",
+            "PRAGMA table_info(t)",
+            "PRAGMA foreign_keys",
+            "CREATE TABLE t (temp INTEGER)",
+            "CREATE VIEW v AS SELECT temp FROM t",
+            "CREATE TRIGGER x AFTER INSERT ON t BEGIN INSERT INTO temp.log VALUES (1); END",
+            "-- ATTACH 'x' AS y",
+        ] {
+            assert!(!changes_state(sql), "{}", sql);
+        }
+    }
+}
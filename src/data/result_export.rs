@@ -0,0 +1,194 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use super::{
+    result_format::{BlobEncoding, ExportOptions, ResultFormat, source_table, write_result},
+    sqlite_database::{SqliteDatabaseState, SqliteDatabaseStateMode},
+};
+use crate::error::AppError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum ExportField {
+    PATH,
+    FORMAT,
+    BLOBS,
+    /// Only shown for the `SQL` format.
+    TABLE,
+}
+
+/// Where and how the current result is written.
+pub struct ExportDialog {
+    pub path: String,
+    pub format: ResultFormat,
+    pub options: ExportOptions,
+    pub focus: ExportField,
+    /// The file exists and Enter was pressed once, the next one overwrites.
+    pub overwrite: bool,
+}
+impl ExportDialog {
+    fn new(table: Option<String>) -> Self {
+        let format = ResultFormat::CSV;
+        let options = ExportOptions {
+            table: table.unwrap_or(ExportOptions::default().table),
+            blobs: BlobEncoding::HEX,
+        };
+        return Self {
+            path: format!("{}.{}", options.table, format.extension()),
+            format,
+            options,
+            focus: ExportField::PATH,
+            overwrite: false,
+        };
+    }
+
+    /// The fields shown, in order.
+    pub fn fields(&self) -> Vec<ExportField> {
+        let mut fields = vec![ExportField::PATH, ExportField::FORMAT, ExportField::BLOBS];
+        if self.format == ResultFormat::SQL {
+            fields.push(ExportField::TABLE);
+        }
+        return fields;
+    }
+
+    fn move_focus(&mut self, step: isize) {
+        let fields = self.fields();
+        let index = fields.iter().position(|f| *f == self.focus).unwrap_or(0);
+        let count = fields.len() as isize;
+        self.focus = fields[(index as isize + step).rem_euclid(count) as usize];
+    }
+
+    /// Changes the format, the extension of the path follows it unless the
+    /// path has a different one.
+    fn cycle_format(&mut self, step: isize) {
+        let old = self.format;
+        self.format = cycle(&ResultFormat::ALL, old, step);
+        if let Some(stem) = self.path.strip_suffix(&format!(".{}", old.extension())) {
+            self.path = format!("{}.{}", stem, self.format.extension());
+        }
+    }
+
+    fn edited_text(&mut self) -> Option<&mut String> {
+        return match self.focus {
+            ExportField::PATH => Some(&mut self.path),
+            ExportField::TABLE => Some(&mut self.options.table),
+            ExportField::FORMAT | ExportField::BLOBS => None,
+        };
+    }
+}
+
+fn cycle<T: Copy + PartialEq>(all: &[T], current: T, step: isize) -> T {
+    let index = all.iter().position(|v| *v == current).unwrap_or(0) as isize;
+    return all[(index + step).rem_euclid(all.len() as isize) as usize];
+}
+
+/// Writing the whole result to a file, not only the rows on screen.
+impl SqliteDatabaseState {
+    pub fn open_export_dialog(&mut self) {
+        let Some(result) = &self.queried_table_state else {
+            return;
+        };
+        if !result.is_readonly() {
+            self.edit_error = Some(AppError::UI(
+                "only results of a query that returns rows can be exported".to_string(),
+            ));
+            return;
+        }
+        let table = source_table(&self.database.connection, &result.sql);
+        self.export_dialog = Some(ExportDialog::new(table));
+        self.export_notice = None;
+        self.error = None;
+        self.mode = SqliteDatabaseStateMode::EXPORT;
+    }
+
+    fn close_export_dialog(&mut self) {
+        self.export_dialog = None;
+        self.error = None;
+        self.mode = SqliteDatabaseStateMode::RESULT_NAVIGATION;
+    }
+
+    /// Writes the result with the dialog's settings. Rows are fetched page by
+    /// page, so this works for results of any size.
+    fn export_result(&mut self) {
+        let (Some(dialog), Some(result)) = (&mut self.export_dialog, &mut self.queried_table_state)
+        else {
+            return;
+        };
+        let path = dialog.path.trim().to_string();
+        if path.is_empty() {
+            self.error = Some(AppError::UI("enter the path of the file".to_string()));
+            return;
+        }
+        if Path::new(&path).exists() && !dialog.overwrite {
+            dialog.overwrite = true;
+            self.error = Some(AppError::UI(format!(
+                "{} exists, Enter again to overwrite it",
+                path
+            )));
+            return;
+        }
+        let connection = &self.database.connection;
+        let written = File::create(&path)
+            .map_err(AppError::from)
+            .and_then(|file| {
+                let mut out = BufWriter::new(file);
+                let rows =
+                    write_result(result, connection, dialog.format, &dialog.options, &mut out)?;
+                out.flush()?;
+                return Ok(rows);
+            });
+        // writing paged through the result, load what the grid shows again
+        let (visible_rows, _) = self.result_viewport.get();
+        let _ = result.ensure_window(connection, self.result_scroll.0, visible_rows);
+        match written {
+            Ok(rows) => {
+                self.export_notice = Some(format!("Exported {} rows to {}", rows, path));
+                self.close_export_dialog();
+            }
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    pub(crate) fn read_export_keys(&mut self, event: &KeyEvent) {
+        let Some(dialog) = &mut self.export_dialog else {
+            self.mode = SqliteDatabaseStateMode::RESULT_NAVIGATION;
+            return;
+        };
+        match (event.code, dialog.focus) {
+            (KeyCode::Esc, _) => self.close_export_dialog(),
+            (KeyCode::Enter, _) => self.export_result(),
+            (KeyCode::Down | KeyCode::Tab, _) => dialog.move_focus(1),
+            (KeyCode::Up | KeyCode::BackTab, _) => dialog.move_focus(-1),
+            (KeyCode::Left, ExportField::FORMAT) => dialog.cycle_format(-1),
+            (KeyCode::Right, ExportField::FORMAT) => dialog.cycle_format(1),
+            (KeyCode::Left, ExportField::BLOBS) => {
+                dialog.options.blobs = cycle(&BlobEncoding::ALL, dialog.options.blobs, -1)
+            }
+            (KeyCode::Right, ExportField::BLOBS) => {
+                dialog.options.blobs = cycle(&BlobEncoding::ALL, dialog.options.blobs, 1)
+            }
+            (KeyCode::Backspace, _) => {
+                dialog.overwrite = false;
+                if let Some(text) = dialog.edited_text() {
+                    text.pop();
+                }
+            }
+            (KeyCode::Char(c), _)
+                if !event
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                dialog.overwrite = false;
+                if let Some(text) = dialog.edited_text() {
+                    text.push(c);
+                }
+            }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,539 @@
+use std::io::Write;
+
+use clap::ValueEnum;
+use rusqlite::{Connection, types::Value};
+
+use super::{
+    cell_editor::single_table_select, identifier::quote_identifier_if_needed,
+    sqlite_query::SqliteQueryResult,
+};
+use crate::error::AppError;
+
+/// Widest a column gets in the `table` format, longer values overflow it.
+const MAX_TABLE_COLUMN_WIDTH: usize = 40;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+#[value(rename_all = "lower")]
+#[allow(clippy::upper_case_acronyms)]
+pub enum ResultFormat {
+    CSV,
+    TSV,
+    JSON,
+    JSONL,
+    MARKDOWN,
+    TABLE,
+    /// `INSERT` statements for `ExportOptions::table`.
+    SQL,
+    HTML,
+}
+impl ResultFormat {
+    pub const ALL: [ResultFormat; 8] = [
+        ResultFormat::CSV,
+        ResultFormat::TSV,
+        ResultFormat::JSON,
+        ResultFormat::JSONL,
+        ResultFormat::MARKDOWN,
+        ResultFormat::TABLE,
+        ResultFormat::SQL,
+        ResultFormat::HTML,
+    ];
+
+    /// File extension used for exports, also the name on the command line.
+    pub fn extension(&self) -> &'static str {
+        return match self {
+            ResultFormat::CSV => "csv",
+            ResultFormat::TSV => "tsv",
+            ResultFormat::JSON => "json",
+            ResultFormat::JSONL => "jsonl",
+            ResultFormat::MARKDOWN => "md",
+            ResultFormat::TABLE => "txt",
+            ResultFormat::SQL => "sql",
+            ResultFormat::HTML => "html",
+        };
+    }
+}
+
+/// How blobs are written. SQL always gets blob literals unless they are
+/// skipped.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+#[value(rename_all = "lower")]
+#[allow(clippy::upper_case_acronyms)]
+pub enum BlobEncoding {
+    HEX,
+    BASE64,
+    /// Written like NULL.
+    SKIP,
+}
+impl BlobEncoding {
+    pub const ALL: [BlobEncoding; 3] =
+        [BlobEncoding::HEX, BlobEncoding::BASE64, BlobEncoding::SKIP];
+
+    fn encode(&self, bytes: &[u8]) -> Option<String> {
+        return match self {
+            BlobEncoding::HEX => Some(hex(bytes)),
+            BlobEncoding::BASE64 => Some(base64(bytes)),
+            BlobEncoding::SKIP => None,
+        };
+    }
+}
+
+/// Settings of the formats that need more than the rows.
+#[derive(Clone, Debug)]
+pub struct ExportOptions {
+    /// Table the `SQL` format inserts into.
+    pub table: String,
+    pub blobs: BlobEncoding,
+}
+impl Default for ExportOptions {
+    fn default() -> Self {
+        return Self {
+            table: "exported".to_string(),
+            blobs: BlobEncoding::HEX,
+        };
+    }
+}
+
+/// The table `sql` selects from when it reads a single one, the default
+/// target of the `SQL` format.
+pub fn source_table(connection: &Connection, sql: &str) -> Option<String> {
+    return single_table_select(connection, sql)
+        .ok()
+        .map(|(_, _, table)| table);
+}
+
+/// Writes every row of `result` to `out`, streaming them from the statement.
+/// Returns the number of rows written.
+pub fn write_result(
+    result: &mut SqliteQueryResult,
+    connection: &Connection,
+    format: ResultFormat,
+    options: &ExportOptions,
+    out: &mut impl Write,
+) -> Result<usize, AppError> {
+    let columns = result.column_names().to_vec();
+    let blobs = options.blobs;
+    let text_value = |value: &Value| text_value(value, blobs);
+    let mut rows = 0;
+    let mut count = |_: &[Value]| rows += 1;
+    match format {
+        ResultFormat::CSV => {
+            write_delimited_row(out, columns.iter().map(|c| csv_field(c)), ",", "\r\n")?;
+            result.for_each_row(connection, |row| {
+                count(row);
+                write_delimited_row(
+                    out,
+                    row.iter().map(|v| csv_field(&text_value(v))),
+                    ",",
+                    "\r\n",
+                )
+            })?;
+        }
+        ResultFormat::TSV => {
+            write_delimited_row(out, columns.iter().map(|c| tsv_field(c)), "\t", "\n")?;
+            result.for_each_row(connection, |row| {
+                count(row);
+                write_delimited_row(
+                    out,
+                    row.iter().map(|v| tsv_field(&text_value(v))),
+                    "\t",
+                    "\n",
+                )
+            })?;
+        }
+        ResultFormat::JSON => {
+            write!(out, "[")?;
+            let mut first = true;
+            result.for_each_row(connection, |row| {
+                count(row);
+                write!(
+                    out,
+                    "{}\n  {}",
+                    if first { "" } else { "," },
+                    json_object(&columns, row, blobs)
+                )?;
+                first = false;
+                return Ok::<(), AppError>(());
+            })?;
+            writeln!(out, "{}]", if first { "" } else { "\n" })?;
+        }
+        ResultFormat::JSONL => {
+            result.for_each_row(connection, |row| {
+                count(row);
+                writeln!(out, "{}", json_object(&columns, row, blobs))?;
+                return Ok::<(), AppError>(());
+            })?;
+        }
+        ResultFormat::MARKDOWN => {
+            writeln!(
+                out,
+                "| {} |",
+                columns
+                    .iter()
+                    .map(|c| markdown_cell(c))
+                    .collect::<Vec<String>>()
+                    .join(" | ")
+            )?;
+            writeln!(out, "|{}", " --- |".repeat(columns.len()))?;
+            result.for_each_row(connection, |row| {
+                count(row);
+                let cells: Vec<String> =
+                    row.iter().map(|v| markdown_cell(&text_value(v))).collect();
+                writeln!(out, "| {} |", cells.join(" | "))?;
+                return Ok::<(), AppError>(());
+            })?;
+        }
+        ResultFormat::TABLE => {
+            // widths come from the first page so the output can still be streamed
+            let mut widths: Vec<usize> = columns.iter().map(|c| c.chars().count()).collect();
+            for row in &result.rows {
+                for (width, value) in widths.iter_mut().zip(row) {
+                    *width = (*width).max(text_value(value).chars().count());
+                }
+            }
+            for width in widths.iter_mut() {
+                *width = (*width).min(MAX_TABLE_COLUMN_WIDTH);
+            }
+            let separator = format!(
+                "+{}+",
+                widths
+                    .iter()
+                    .map(|w| "-".repeat(w + 2))
+                    .collect::<Vec<String>>()
+                    .join("+")
+            );
+            writeln!(out, "{}", separator)?;
+            write_table_row(out, &widths, columns.iter().map(|c| c.to_string()))?;
+            writeln!(out, "{}", separator)?;
+            result.for_each_row(connection, |row| {
+                count(row);
+                write_table_row(out, &widths, row.iter().map(text_value))
+            })?;
+            writeln!(out, "{}", separator)?;
+        }
+        ResultFormat::SQL => {
+            let insert = format!(
+                "INSERT INTO {} ({}) VALUES",
+                quote_identifier_if_needed(&options.table),
+                columns
+                    .iter()
+                    .map(|c| quote_identifier_if_needed(c))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            );
+            result.for_each_row(connection, |row| {
+                count(row);
+                let values: Vec<String> = row.iter().map(|v| sql_literal(v, blobs)).collect();
+                writeln!(out, "{} ({});", insert, values.join(", "))?;
+                return Ok::<(), AppError>(());
+            })?;
+        }
+        ResultFormat::HTML => {
+            writeln!(out, "<table>")?;
+            writeln!(
+                out,
+                "  <thead><tr>{}</tr></thead>",
+                columns
+                    .iter()
+                    .map(|c| format!("<th>{}</th>", html_escape(c)))
+                    .collect::<String>()
+            )?;
+            writeln!(out, "  <tbody>")?;
+            result.for_each_row(connection, |row| {
+                count(row);
+                let cells: String = row
+                    .iter()
+                    .map(|v| format!("<td>{}</td>", html_escape(&text_value(v))))
+                    .collect();
+                writeln!(out, "    <tr>{}</tr>", cells)?;
+                return Ok::<(), AppError>(());
+            })?;
+            writeln!(out, "  </tbody>")?;
+            writeln!(out, "</table>")?;
+        }
+    }
+    return Ok(rows);
+}
+
+/// Plain text for a value in the text based formats, NULL and skipped blobs
+/// are empty.
+pub fn text_value(value: &Value, blobs: BlobEncoding) -> String {
+    return match value {
+        Value::Null => String::new(),
+        Value::Integer(i) => i.to_string(),
+        Value::Real(f) => f.to_string(),
+        Value::Text(s) => s.to_string(),
+        Value::Blob(bytes) => blobs.encode(bytes).unwrap_or_default(),
+    };
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    return bytes.iter().map(|b| format!("{:02X}", b)).collect();
+}
+
+/// Standard alphabet with padding.
+pub fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |group, (i, b)| group | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    return encoded;
+}
+
+/// A value as SQLite reads it back, reals keep their fraction so they stay
+/// REAL.
+pub fn sql_literal(value: &Value, blobs: BlobEncoding) -> String {
+    return match value {
+        Value::Null => "NULL".to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Real(f) if f.is_infinite() => {
+            (if *f > 0.0 { "9e999" } else { "-9e999" }).to_string()
+        }
+        // SQLite has no NaN, it reads one back as NULL
+        Value::Real(f) if f.is_nan() => "NULL".to_string(),
+        Value::Real(f) => format!("{:?}", f),
+        Value::Text(s) => format!("'{}'", s.replace('\'', "''")),
+        Value::Blob(_) if blobs == BlobEncoding::SKIP => "NULL".to_string(),
+        Value::Blob(bytes) => format!("X'{}'", hex(bytes)),
+    };
+}
+
+fn html_escape(text: &str) -> String {
+    return text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;");
+}
+
+/// CSV ends records with CRLF as RFC 4180 asks, TSV with a plain newline.
+fn write_delimited_row(
+    out: &mut impl Write,
+    fields: impl Iterator<Item = String>,
+    delimiter: &str,
+    line_end: &str,
+) -> Result<(), AppError> {
+    let record = fields.collect::<Vec<String>>().join(delimiter);
+    write!(out, "{}{}", record, line_end)?;
+    return Ok(());
+}
+
+fn write_table_row(
+    out: &mut impl Write,
+    widths: &[usize],
+    cells: impl Iterator<Item = String>,
+) -> Result<(), AppError> {
+    let cells: Vec<String> = cells
+        .zip(widths)
+        .map(|(cell, width)| format!(" {:<width$} ", cell.replace('\n', " "), width = width))
+        .collect();
+    writeln!(out, "|{}|", cells.join("|"))?;
+    return Ok(());
+}
+
+/// RFC 4180 field: quoted when it contains a delimiter, quote or line break.
+pub fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        return format!("\"{}\"", field.replace('"', "\"\""));
+    }
+    return field.to_string();
+}
+
+fn tsv_field(field: &str) -> String {
+    return field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r");
+}
+
+fn markdown_cell(field: &str) -> String {
+    return field.replace('|', "\\|").replace('\n', "<br>");
+}
+
+pub fn json_value(value: &Value, blobs: BlobEncoding) -> serde_json::Value {
+    return match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Integer(i) => serde_json::Value::from(*i),
+        Value::Real(f) => serde_json::Number::from_f64(*f)
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null),
+        Value::Text(s) => serde_json::Value::from(s.as_str()),
+        Value::Blob(bytes) => blobs
+            .encode(bytes)
+            .map_or(serde_json::Value::Null, serde_json::Value::from),
+    };
+}
+
+/// One row as a JSON object, keys kept in column order.
+pub fn json_object(columns: &[String], row: &[Value], blobs: BlobEncoding) -> String {
+    let fields: Vec<String> = columns
+        .iter()
+        .zip(row)
+        .map(|(column, value)| {
+            format!(
+                "{}: {}",
+                serde_json::Value::from(column.as_str()),
+                json_value(value, blobs)
+            )
+        })
+        .collect();
+    return format!("{{{}}}", fields.join(", "));
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use rusqlite::Connection;
+
+    use super::*;
+
+    /// Runs `sql` on `connection` and writes its result as `format`.
+    pub(crate) fn export_from(
+        connection: &Connection,
+        sql: &str,
+        format: ResultFormat,
+        blobs: BlobEncoding,
+    ) -> String {
+        let columns = connection
+            .prepare(sql)
+            .unwrap()
+            .column_names()
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        let mut result = SqliteQueryResult::query(connection, sql, columns, Vec::new()).unwrap();
+        let options = ExportOptions {
+            blobs,
+            ..ExportOptions::default()
+        };
+        let mut out = Vec::new();
+        write_result(&mut result, connection, format, &options, &mut out).unwrap();
+        return String::from_utf8(out).unwrap();
+    }
+
+    fn export(sql: &str, format: ResultFormat, blobs: BlobEncoding) -> String {
+        return export_from(&Connection::open_in_memory().unwrap(), sql, format, blobs);
+    }
+
+    #[test]
+    fn quotes_csv_fields_only_when_needed() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field(""), "");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("cr\r"), "\"cr\r\"");
+    }
+
+    #[test]
+    fn csv_records_end_with_crlf() {
+        let csv = export(
+            "SELECT 1 AS id, 'a,b' AS \"na,me\" UNION ALL SELECT 2, 'line\none \"q\"' UNION ALL SELECT NULL, ''",
+            ResultFormat::CSV,
+            BlobEncoding::HEX,
+        );
+        assert_eq!(
+            csv,
+            "id,\"na,me\"\r\n1,\"a,b\"\r\n2,\"line\none \"\"q\"\"\"\r\n,\r\n"
+        );
+    }
+
+    #[test]
+    fn tsv_escapes_and_ends_with_newline() {
+        let tsv = export(
+            "SELECT 'a\tb' AS x, 'c\nd\\e' AS y",
+            ResultFormat::TSV,
+            BlobEncoding::HEX,
+        );
+        assert_eq!(tsv, "x\ty\na\\tb\tc\\nd\\\\e\n");
+    }
+
+    #[test]
+    fn encodes_base64() {
+        // test vectors of RFC 4648
+        for (plain, encoded) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(base64(plain.as_bytes()), encoded);
+        }
+        assert_eq!(base64(&[0xFF, 0xFE, 0x00]), "//4A");
+    }
+
+    #[test]
+    fn blobs_follow_the_encoding() {
+        let sql = "SELECT X'00FF10' AS b";
+        assert_eq!(
+            export(sql, ResultFormat::CSV, BlobEncoding::HEX),
+            "b\r\n00FF10\r\n"
+        );
+        assert_eq!(
+            export(sql, ResultFormat::CSV, BlobEncoding::BASE64),
+            "b\r\nAP8Q\r\n"
+        );
+        assert_eq!(
+            export(sql, ResultFormat::CSV, BlobEncoding::SKIP),
+            "b\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn sql_literals_read_back_as_the_same_value() {
+        let connection = Connection::open_in_memory().unwrap();
+        let values = [
+            Value::Null,
+            Value::Integer(-42),
+            Value::Integer(i64::MAX),
+            Value::Real(1.0),
+            Value::Real(-0.125),
+            Value::Real(f64::INFINITY),
+            Value::Text("it's; \"quoted\"\n".to_string()),
+            Value::Blob((0..=255).collect()),
+            Value::Blob(Vec::new()),
+        ];
+        for value in values {
+            let literal = sql_literal(&value, BlobEncoding::HEX);
+            let read: Value = connection
+                .query_row(&format!("SELECT {}", literal), [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(read, value, "{}", literal);
+        }
+        assert_eq!(
+            sql_literal(&Value::Blob(vec![1]), BlobEncoding::SKIP),
+            "NULL"
+        );
+        assert_eq!(
+            sql_literal(&Value::Real(f64::NAN), BlobEncoding::HEX),
+            "NULL"
+        );
+    }
+
+    #[test]
+    fn json_objects_keep_column_order() {
+        let columns = ["b".to_string(), "a".to_string(), "c".to_string()];
+        let row = [
+            Value::Text("x\"y".to_string()),
+            Value::Null,
+            Value::Blob(b"hi".to_vec()),
+        ];
+        assert_eq!(
+            json_object(&columns, &row, BlobEncoding::BASE64),
+            "{\"b\": \"x\\\"y\", \"a\": null, \"c\": \"aGk=\"}"
+        );
+    }
+}
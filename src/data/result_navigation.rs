@@ -0,0 +1,146 @@
+use super::sqlite_database::SqliteDatabaseState;
+
+/// Cell selection and scrolling for the result grid. `result_cursor` and
+/// `result_scroll` are both `(row, column)`.
+impl SqliteDatabaseState {
+    pub fn reset_result_navigation(&mut self) {
+        self.result_cursor = (0, 0);
+        self.result_scroll = (0, 0);
+    }
+
+    fn result_size(&self) -> (usize, usize) {
+        return match &self.queried_table_state {
+            Some(result) => (result.row_count(), result.column_names().len()),
+            None => (0, 0),
+        };
+    }
+
+    pub fn move_result_cursor(&mut self, rows: isize, columns: isize) {
+        let (row, column) = self.result_cursor;
+        self.move_result_cursor_to(
+            Some(row.saturating_add_signed(rows)),
+            Some(column.saturating_add_signed(columns)),
+        );
+    }
+
+    pub fn move_result_cursor_to(&mut self, row: Option<usize>, column: Option<usize>) {
+        if let Some(row) = row {
+            // rows past the ones seen so far may exist while the total is unknown
+            self.load_result_rows(row, 1);
+        }
+        let (row_count, column_count) = self.result_size();
+        if row_count == 0 || column_count == 0 {
+            return;
+        }
+        if let Some(row) = row {
+            self.result_cursor.0 = row.min(row_count - 1);
+        }
+        if let Some(column) = column {
+            self.result_cursor.1 = column.min(column_count - 1);
+        }
+        self.scroll_to_result_cursor();
+        let (row_offset, _) = self.result_scroll;
+        self.load_result_rows(row_offset, self.result_viewport.get().0);
+    }
+
+    /// Jumps to the last row. When the rows still have to be counted the
+    /// jump waits for `count_result_rows`.
+    pub fn move_result_cursor_to_end(&mut self) {
+        if let Some(result) = &self.queried_table_state
+            && !result.is_complete()
+        {
+            self.count_result_rows();
+            return;
+        }
+        self.move_result_cursor_to(Some(usize::MAX), None);
+    }
+
+    /// Fetches the page holding `offset..offset + length` when it isn't cached.
+    fn load_result_rows(&mut self, offset: usize, length: usize) {
+        let Some(result) = &mut self.queried_table_state else {
+            return;
+        };
+        let offset = match result.total_rows {
+            Some(total) => offset.min(total.saturating_sub(1)),
+            None => offset,
+        };
+        if let Err(e) = result.ensure_window(&self.database.connection, offset, length) {
+            self.error = Some(e.into());
+        }
+    }
+
+    /// Number of rows PageUp/PageDown moves, based on the last rendered grid.
+    pub fn result_page_size(&self) -> isize {
+        return self.result_viewport.get().0.max(1) as isize;
+    }
+
+    /// Adjusts `result_scroll` so the selected cell is inside the viewport
+    /// the grid was last rendered with.
+    fn scroll_to_result_cursor(&mut self) {
+        let (visible_rows, visible_columns) = self.result_viewport.get();
+        let (visible_rows, visible_columns) = (visible_rows.max(1), visible_columns.max(1));
+        let (row, column) = self.result_cursor;
+        let (row_offset, column_offset) = &mut self.result_scroll;
+        if row < *row_offset {
+            *row_offset = row;
+        } else if row >= *row_offset + visible_rows {
+            *row_offset = row + 1 - visible_rows;
+        }
+        if column < *column_offset {
+            *column_offset = column;
+        } else if column >= *column_offset + visible_columns {
+            *column_offset = column + 1 - visible_columns;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::super::sqlite_database::SqliteDatabase;
+    use super::*;
+
+    /// A 5 x 3 result shown in a 2 x 2 viewport.
+    fn grid() -> SqliteDatabaseState {
+        let database = SqliteDatabase::open(PathBuf::from(":memory:"), false, true).unwrap();
+        let mut state = SqliteDatabaseState::new(&database);
+        state.current_query = "WITH RECURSIVE n(a) AS (SELECT 1 UNION ALL SELECT a + 1 FROM n \
+                               WHERE a < 5) SELECT a, a * 2 AS b, a * 3 AS c FROM n"
+            .to_string();
+        state.execute();
+        state.result_viewport.set((2, 2));
+        return state;
+    }
+
+    #[test]
+    fn the_cursor_stays_inside_the_result() {
+        let mut state = grid();
+        state.move_result_cursor(-1, -1);
+        assert_eq!(state.result_cursor, (0, 0));
+        state.move_result_cursor(100, 100);
+        assert_eq!(state.result_cursor, (4, 2));
+        state.move_result_cursor_to(Some(1), None);
+        assert_eq!(state.result_cursor, (1, 2));
+    }
+
+    #[test]
+    fn scrolling_follows_the_cursor() {
+        let mut state = grid();
+        state.move_result_cursor(3, 2);
+        assert_eq!(state.result_scroll, (2, 1));
+        state.move_result_cursor(-1, 0);
+        assert_eq!(state.result_scroll, (2, 1));
+        state.move_result_cursor_to(Some(0), Some(0));
+        assert_eq!(state.result_scroll, (0, 0));
+    }
+
+    #[test]
+    fn an_empty_result_has_nothing_to_select() {
+        let mut state = grid();
+        state.current_query = "SELECT 1 WHERE 0".to_string();
+        state.execute();
+        state.move_result_cursor(1, 1);
+        assert_eq!(state.result_cursor, (0, 0));
+    }
+}
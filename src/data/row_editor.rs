@@ -0,0 +1,531 @@
+use std::collections::BTreeSet;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use rusqlite::{Connection, params_from_iter, types::Value};
+
+use super::{
+    cell_editor::single_table_select,
+    identifier::quote_identifier,
+    object_tree::SchemaObjectKind,
+    sqlite_database::{
+        SqliteDatabaseState, SqliteDatabaseStateMode, pending_edits_error, read_only_error,
+    },
+    sqlite_table::{SqliteColumn, SqliteTable},
+};
+use crate::error::AppError;
+
+/// Keys listed in the delete confirmation before it only shows a count.
+pub const MAX_LISTED_KEYS: usize = 10;
+
+/// Type affinity of a column, derived from its declared type with the rules
+/// from https://www.sqlite.org/datatype3.html#determination_of_column_affinity
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum Affinity {
+    INTEGER,
+    TEXT,
+    BLOB,
+    REAL,
+    NUMERIC,
+}
+impl Affinity {
+    pub fn of(declared_type: &str) -> Self {
+        let declared_type = declared_type.to_uppercase();
+        if declared_type.contains("INT") {
+            return Affinity::INTEGER;
+        }
+        if ["CHAR", "CLOB", "TEXT"]
+            .iter()
+            .any(|t| declared_type.contains(t))
+        {
+            return Affinity::TEXT;
+        }
+        if declared_type.is_empty() || declared_type.contains("BLOB") {
+            return Affinity::BLOB;
+        }
+        if ["REAL", "FLOA", "DOUB"]
+            .iter()
+            .any(|t| declared_type.contains(t))
+        {
+            return Affinity::REAL;
+        }
+        return Affinity::NUMERIC;
+    }
+
+    /// Converts typed text the way SQLite converts text stored in a column
+    /// of this affinity: the numeric affinities turn well-formed numbers into
+    /// INTEGER or REAL values and keep any other text as it is.
+    pub fn parse(&self, text: &str) -> Value {
+        let trimmed = text.trim();
+        let is_number = !trimmed.is_empty()
+            && trimmed
+                .chars()
+                .all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'));
+        let real = trimmed.parse::<f64>().ok().filter(|_| is_number);
+        return match (self, real) {
+            (Affinity::TEXT | Affinity::BLOB, _) | (_, None) => Value::Text(text.to_string()),
+            (Affinity::REAL, Some(real)) => Value::Real(real),
+            (Affinity::INTEGER | Affinity::NUMERIC, Some(real)) => match trimmed.parse() {
+                Ok(integer) => Value::Integer(integer),
+                // reals without a fractional part that fit are stored as integers
+                Err(_) if real.fract() == 0.0 && real.abs() < i64::MAX as f64 => {
+                    Value::Integer(real as i64)
+                }
+                Err(_) => Value::Real(real),
+            },
+        };
+    }
+}
+
+pub struct RowFormField {
+    pub column: SqliteColumn,
+    pub affinity: Affinity,
+    pub text: String,
+    /// Set with Ctrl+N, inserts NULL instead of the text.
+    pub null: bool,
+    /// The column's default expression, shown while the text is empty. An
+    /// empty field is left out of the INSERT so SQLite evaluates it itself.
+    pub placeholder: Option<String>,
+    /// The column is the table's only primary key column and declared
+    /// `INTEGER`, an alias of the rowid that gets assigned when left empty.
+    rowid_alias: bool,
+}
+impl RowFormField {
+    fn new(column: SqliteColumn, key_columns: usize) -> Self {
+        let placeholder = match &column.default_value {
+            Value::Null => None,
+            Value::Integer(i) => Some(i.to_string()),
+            Value::Real(f) => Some(f.to_string()),
+            Value::Text(s) => Some(s.clone()),
+            Value::Blob(_) => None,
+        };
+        return Self {
+            affinity: Affinity::of(&column.declared_type),
+            text: String::new(),
+            null: false,
+            placeholder,
+            rowid_alias: key_columns == 1
+                && column.pk > 0
+                && column.declared_type.eq_ignore_ascii_case("INTEGER"),
+            column,
+        };
+    }
+
+    pub fn is_required(&self) -> bool {
+        return self.column.not_null && self.placeholder.is_none() && !self.rowid_alias;
+    }
+
+    /// The value to insert, `Ok(None)` when the column is left out.
+    fn value(&self) -> Result<Option<Value>, AppError> {
+        if self.null {
+            return Ok(Some(Value::Null));
+        }
+        if self.text.is_empty() {
+            if self.is_required() {
+                return Err(AppError::UI(format!("{} is required", self.column.name)));
+            }
+            return Ok(None);
+        }
+        return Ok(Some(self.affinity.parse(&self.text)));
+    }
+}
+
+/// Form for inserting one row into `table`.
+pub struct RowForm {
+    pub schema: String,
+    pub table: String,
+    pub fields: Vec<RowFormField>,
+    pub focus: usize,
+    /// Mode to go back to when the form closes.
+    pub return_mode: SqliteDatabaseStateMode,
+}
+impl RowForm {
+    pub fn new(
+        table: &SqliteTable,
+        return_mode: SqliteDatabaseStateMode,
+    ) -> Result<Self, AppError> {
+        let columns = table.columns()?;
+        let key_columns = columns.values().filter(|c| c.pk > 0).count();
+        return Ok(Self {
+            schema: table.schema.clone(),
+            table: table.name.clone(),
+            fields: columns
+                .into_values()
+                .map(|column| RowFormField::new(column, key_columns))
+                .collect(),
+            focus: 0,
+            return_mode,
+        });
+    }
+
+    /// Validates the fields and runs a parameterized INSERT.
+    pub fn insert(&self, connection: &Connection) -> Result<(), AppError> {
+        let mut columns = Vec::new();
+        let mut values = Vec::new();
+        for field in &self.fields {
+            if let Some(value) = field.value()? {
+                columns.push(quote_identifier(&field.column.name));
+                values.push(value);
+            }
+        }
+        let target = format!(
+            "{}.{}",
+            quote_identifier(&self.schema),
+            quote_identifier(&self.table)
+        );
+        if columns.is_empty() {
+            connection.execute(&format!("INSERT INTO {} DEFAULT VALUES", target), [])?;
+            return Ok(());
+        }
+        let placeholders: Vec<String> = (1..=values.len()).map(|i| format!("?{}", i)).collect();
+        connection.execute(
+            &format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                target,
+                columns.join(", "),
+                placeholders.join(", ")
+            ),
+            params_from_iter(values),
+        )?;
+        return Ok(());
+    }
+
+    fn focused_field(&mut self) -> Option<&mut RowFormField> {
+        return self.fields.get_mut(self.focus);
+    }
+}
+
+/// Rows waiting for the user to confirm their deletion.
+pub struct DeleteConfirmation {
+    pub table: String,
+    pub key_names: Vec<String>,
+    /// Key values of every row, in result order.
+    pub keys: Vec<Vec<Value>>,
+}
+
+/// Inserting rows through a form and deleting the selected ones.
+impl SqliteDatabaseState {
+    /// Opens the insert form for the table the current result comes from,
+    /// or the table selected in the object tree.
+    pub fn open_row_form(&mut self) {
+        self.edit_error = None;
+        if self.database.readonly {
+            match self.mode {
+                SqliteDatabaseStateMode::RESULT_NAVIGATION => {
+                    self.edit_error = Some(read_only_error())
+                }
+                _ => self.error = Some(read_only_error()),
+            }
+            return;
+        }
+        let table = match self.mode {
+            SqliteDatabaseStateMode::RESULT_NAVIGATION => {
+                let Some(result) = &self.queried_table_state else {
+                    return;
+                };
+                match single_table_select(&self.database.connection, &result.sql) {
+                    Ok((_, schema, table)) => {
+                        SqliteTable::in_schema(schema, table, self.database.connection.clone())
+                    }
+                    Err(e) => {
+                        self.edit_error = Some(e);
+                        return;
+                    }
+                }
+            }
+            _ => match self.object_tree.selected_object() {
+                Some(object) if object.kind == SchemaObjectKind::TABLE => SqliteTable::in_schema(
+                    object.schema.clone(),
+                    object.name.clone(),
+                    self.database.connection.clone(),
+                ),
+                _ => return,
+            },
+        };
+        match RowForm::new(&table, self.mode) {
+            Ok(form) => {
+                self.error = None;
+                self.row_form = Some(form);
+                self.mode = SqliteDatabaseStateMode::ROW_INSERT;
+            }
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    fn close_row_form(&mut self) {
+        if let Some(form) = self.row_form.take() {
+            self.mode = form.return_mode;
+        }
+        self.error = None;
+    }
+
+    pub fn insert_row(&mut self) {
+        let Some(form) = &self.row_form else {
+            return;
+        };
+        if let Err(e) = form.insert(&self.database.connection) {
+            self.error = Some(e);
+            return;
+        }
+        self.close_row_form();
+        if self.mode == SqliteDatabaseStateMode::RESULT_NAVIGATION {
+            self.refresh_result();
+        }
+    }
+
+    pub(crate) fn read_row_form_keys(&mut self, event: &KeyEvent) {
+        let Some(form) = &mut self.row_form else {
+            self.mode = SqliteDatabaseStateMode::TABLE_SELECTION;
+            return;
+        };
+        let control = event.modifiers.contains(KeyModifiers::CONTROL);
+        let count = form.fields.len();
+        match event.code {
+            KeyCode::Esc => self.close_row_form(),
+            KeyCode::Enter => self.insert_row(),
+            KeyCode::Down | KeyCode::Tab if count > 0 => form.focus = (form.focus + 1) % count,
+            KeyCode::Up | KeyCode::BackTab if count > 0 => {
+                form.focus = (form.focus + count - 1) % count
+            }
+            KeyCode::Char('n') if control => {
+                if let Some(field) = form.focused_field() {
+                    field.null = !field.null;
+                }
+            }
+            KeyCode::Backspace => {
+                if let Some(field) = form.focused_field() {
+                    field.null = false;
+                    field.text.pop();
+                }
+            }
+            KeyCode::Char(c)
+                if !event
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                if let Some(field) = form.focused_field() {
+                    field.null = false;
+                    field.text.push(c);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Marks or unmarks the row under the cursor for deletion.
+    pub fn toggle_row_mark(&mut self) {
+        let row = self.result_cursor.0;
+        if !self.marked_rows.remove(&row) {
+            self.marked_rows.insert(row);
+        }
+    }
+
+    /// Asks for confirmation to delete the marked rows, or the row under the
+    /// cursor when none are marked.
+    pub fn request_delete(&mut self) {
+        self.edit_error = None;
+        if self.database.readonly {
+            self.edit_error = Some(read_only_error());
+            return;
+        }
+        if !self.pending_edits.is_empty() {
+            self.edit_error = Some(pending_edits_error());
+            return;
+        }
+        match &self.queried_table_state {
+            Some(result) if result.row_count() > 0 => (),
+            _ => return,
+        }
+        if !self.ensure_edit_target() {
+            return;
+        }
+        let rows: BTreeSet<usize> = if self.marked_rows.is_empty() {
+            BTreeSet::from([self.result_cursor.0])
+        } else {
+            self.marked_rows.clone()
+        };
+        let (Some(result), Some(target)) = (&mut self.queried_table_state, &self.edit_target)
+        else {
+            return;
+        };
+        let mut keys = Vec::new();
+        for row in rows {
+            // marked rows can be far apart, fetch each one's page
+            if let Err(e) = result.ensure_window(&self.database.connection, row, 1) {
+                self.edit_error = Some(e.into());
+                return;
+            }
+            if let Some(values) = result.window(row, 1).first() {
+                keys.push(target.key_values(values));
+            }
+        }
+        self.delete_confirmation = Some(DeleteConfirmation {
+            table: target.table.clone(),
+            key_names: target.key_names(),
+            keys,
+        });
+    }
+
+    /// Deletes the confirmed rows inside one savepoint, nothing is deleted if
+    /// any of the statements fails.
+    pub fn confirm_delete(&mut self) {
+        let (Some(confirmation), Some(target)) =
+            (self.delete_confirmation.take(), &self.edit_target)
+        else {
+            return;
+        };
+        let connection = &self.database.connection;
+        let sql = target.delete_sql();
+        let apply = || -> Result<(), AppError> {
+            let mut stmt = connection.prepare(&sql)?;
+            for key in &confirmation.keys {
+                stmt.execute(params_from_iter(key))?;
+            }
+            return Ok(());
+        };
+        let outcome = connection
+            .execute_batch("SAVEPOINT delete_rows")
+            .map_err(AppError::from)
+            .and_then(|_| apply());
+        match outcome {
+            Ok(()) => {
+                if let Err(e) = connection.execute_batch("RELEASE delete_rows") {
+                    self.edit_error = Some(e.into());
+                    return;
+                }
+                self.marked_rows.clear();
+                self.refresh_result();
+            }
+            Err(e) => {
+                let _ = connection.execute_batch("ROLLBACK TO delete_rows; RELEASE delete_rows");
+                self.edit_error = Some(e);
+            }
+        }
+    }
+
+    /// Keys of the result grid for inserting and deleting rows, returns false
+    /// for the ones it leaves to navigation.
+    pub(crate) fn read_row_keys(&mut self, event: &KeyEvent) -> bool {
+        if self.delete_confirmation.is_some() {
+            match event.code {
+                KeyCode::Enter | KeyCode::Char('y') => self.confirm_delete(),
+                KeyCode::Esc | KeyCode::Char('n') => self.delete_confirmation = None,
+                _ => (),
+            }
+            return true;
+        }
+        if self.cell_editor.is_some() {
+            return false;
+        }
+        match event.code {
+            KeyCode::Insert => self.open_row_form(),
+            KeyCode::Delete => self.request_delete(),
+            KeyCode::Char(' ') => self.toggle_row_mark(),
+            _ => return false,
+        }
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn affinity_follows_the_declared_type() {
+        for (declared_type, affinity) in [
+            ("INT", Affinity::INTEGER),
+            ("unsigned big int", Affinity::INTEGER),
+            ("VARCHAR(255)", Affinity::TEXT),
+            ("CLOB", Affinity::TEXT),
+            ("", Affinity::BLOB),
+            ("BLOB", Affinity::BLOB),
+            ("DOUBLE PRECISION", Affinity::REAL),
+            ("FLOAT", Affinity::REAL),
+            ("DECIMAL(10,5)", Affinity::NUMERIC),
+            ("BOOLEAN", Affinity::NUMERIC),
+            // INT wins over the other rules, even inside another word
+            ("FLOATING POINT", Affinity::INTEGER),
+            ("CHARINT", Affinity::INTEGER),
+        ] {
+            assert_eq!(Affinity::of(declared_type), affinity, "{}", declared_type);
+        }
+    }
+
+    #[test]
+    fn typed_text_is_converted_like_sqlite_does() {
+        let text = |s: &str| Value::Text(s.to_string());
+        assert_eq!(Affinity::INTEGER.parse(" 42 "), Value::Integer(42));
+        assert_eq!(Affinity::INTEGER.parse("1.5"), Value::Real(1.5));
+        assert_eq!(Affinity::INTEGER.parse("2.0"), Value::Integer(2));
+        assert_eq!(Affinity::INTEGER.parse("forty"), text("forty"));
+        assert_eq!(Affinity::INTEGER.parse("inf"), text("inf"));
+        assert_eq!(Affinity::REAL.parse("3"), Value::Real(3.0));
+        assert_eq!(Affinity::NUMERIC.parse("1e3"), Value::Integer(1000));
+        assert_eq!(Affinity::NUMERIC.parse("3.5"), Value::Real(3.5));
+        assert_eq!(Affinity::TEXT.parse(" 42 "), text(" 42 "));
+
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch("CREATE TABLE t (i INTEGER, r REAL, n NUMERIC, x TEXT)")
+            .unwrap();
+        for typed in ["7", " -3 ", "1.5", "2.0", "1e3", "12abc", "0x10", "NaN", ""] {
+            connection
+                .execute("INSERT INTO t VALUES (?1, ?1, ?1, ?1)", [typed])
+                .unwrap();
+            let stored: Vec<Value> = connection
+                .query_row(
+                    "SELECT i, r, n, x FROM t WHERE rowid = last_insert_rowid()",
+                    [],
+                    |row| (0..4).map(|i| row.get(i)).collect(),
+                )
+                .unwrap();
+            let parsed: Vec<Value> = [
+                Affinity::INTEGER,
+                Affinity::REAL,
+                Affinity::NUMERIC,
+                Affinity::TEXT,
+            ]
+            .iter()
+            .map(|affinity| affinity.parse(typed))
+            .collect();
+            assert_eq!(parsed, stored, "{:?}", typed);
+        }
+    }
+
+    #[test]
+    fn only_a_single_integer_key_is_filled_in_by_sqlite() {
+        let connection = Rc::new(Connection::open_in_memory().unwrap());
+        connection
+            .execute_batch(
+                "CREATE TABLE single (id INTEGER PRIMARY KEY, name TEXT NOT NULL DEFAULT 'x', n INT NOT NULL);
+                 CREATE TABLE pair (a INTEGER NOT NULL, b INTEGER NOT NULL, PRIMARY KEY (a, b));",
+            )
+            .unwrap();
+        let form = |name: &str| {
+            let table = SqliteTable::new(name.to_string(), connection.clone());
+            return RowForm::new(&table, SqliteDatabaseStateMode::TABLE_SELECTION).unwrap();
+        };
+        let required = |form: &RowForm| -> Vec<bool> {
+            return form.fields.iter().map(|f| f.is_required()).collect();
+        };
+
+        let mut single = form("single");
+        assert_eq!(required(&single), vec![false, false, true]);
+        assert_eq!(single.fields[1].placeholder.as_deref(), Some("'x'"));
+        assert_eq!(single.fields[1].text, "");
+        assert!(single.insert(&connection).is_err());
+        single.fields[2].text = "1.5".to_string();
+        single.insert(&connection).unwrap();
+        let row: (i64, String, f64) = connection
+            .query_row("SELECT * FROM single", [], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(row, (1, "x".to_string(), 1.5));
+
+        assert_eq!(required(&form("pair")), vec![true, true]);
+    }
+}
@@ -0,0 +1,337 @@
+use std::time::{Duration, Instant};
+
+use rusqlite::{Connection, types::Value};
+use sqlparser::{
+    dialect::SQLiteDialect,
+    keywords::Keyword,
+    tokenizer::{Location, Token, Tokenizer},
+};
+
+use super::{
+    query_worker::run_statement,
+    sqlite_query::{QueryStats, SqliteQueryResult},
+};
+use crate::error::AppError;
+
+/// Savepoint wrapping a script run as a single transaction. A savepoint
+/// instead of `BEGIN` so it also works inside a transaction that is open.
+const SCRIPT_SAVEPOINT: &str = "script";
+
+/// How a query with several statements is run, toggled in the query tool.
+#[derive(Debug, Clone, Copy)]
+pub struct ScriptOptions {
+    /// Statements after a failed one are skipped, otherwise they still run.
+    pub stop_on_error: bool,
+    /// All statements run in one transaction that is rolled back when the
+    /// script stops on an error.
+    pub single_transaction: bool,
+}
+impl Default for ScriptOptions {
+    fn default() -> Self {
+        return Self {
+            stop_on_error: true,
+            single_transaction: false,
+        };
+    }
+}
+
+/// What one statement of a script did.
+#[derive(Debug)]
+pub struct StatementReport {
+    pub sql: String,
+    /// The summary of its result, e.g. `3 rows changed`, or its error.
+    pub result: Result<String, String>,
+    pub duration: Duration,
+}
+
+/// Per statement results of a script, shown above the result of the last
+/// statement that ran.
+#[derive(Debug)]
+pub struct ScriptReport {
+    pub statements: Vec<StatementReport>,
+    /// Statements left out after one failed.
+    pub skipped: usize,
+    /// The single transaction was rolled back, nothing the script did stays.
+    pub rolled_back: bool,
+}
+impl ScriptReport {
+    pub fn failed(&self) -> usize {
+        return self.statements.iter().filter(|s| s.result.is_err()).count();
+    }
+}
+
+/// Splits `sql` at the semicolons ending its statements. Semicolons in
+/// strings, comments and trigger bodies don't end a statement, and pieces
+/// with nothing but whitespace and comments are left out. Input the tokenizer
+/// rejects, e.g. with an unterminated string, stays one statement so running
+/// it reports the error.
+pub fn split_statements(sql: &str) -> Vec<String> {
+    let Ok(tokens) = Tokenizer::new(&SQLiteDialect {}, sql).tokenize_with_location() else {
+        return vec![sql.trim().to_string()];
+    };
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(sql.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    // locations count characters from 1, slicing needs byte offsets
+    let offset = |location: Location| -> usize {
+        let Some(&line_start) = line_starts.get((location.line as usize).saturating_sub(1)) else {
+            return sql.len();
+        };
+        return sql[line_start..]
+            .char_indices()
+            .nth((location.column as usize).saturating_sub(1))
+            .map_or(sql.len(), |(i, _)| line_start + i);
+    };
+
+    let mut statements = Vec::new();
+    let mut push = |piece: &str| {
+        let piece = piece.trim();
+        if !piece.is_empty() {
+            statements.push(piece.to_string());
+        }
+    };
+    let mut start = 0;
+    let mut has_content = false;
+    let mut words = 0;
+    let mut creating = false;
+    let mut trigger = false;
+    let mut depth = 0;
+    for token in &tokens {
+        match &token.token {
+            Token::Whitespace(_) => continue,
+            Token::SemiColon if depth == 0 => {
+                if has_content {
+                    push(&sql[start..offset(token.span.start)]);
+                }
+                start = offset(token.span.end);
+                has_content = false;
+                words = 0;
+                creating = false;
+                trigger = false;
+                continue;
+            }
+            // BEGIN ... END of a trigger body may contain CASE ... END
+            Token::Word(w) => match w.keyword {
+                Keyword::CREATE if words == 0 => creating = true,
+                Keyword::TRIGGER if creating && words <= 2 => trigger = true,
+                Keyword::BEGIN | Keyword::CASE if trigger => depth += 1,
+                Keyword::END if trigger && depth > 0 => depth -= 1,
+                _ => (),
+            },
+            _ => (),
+        }
+        has_content = true;
+        words += 1;
+    }
+    if has_content {
+        push(&sql[start..]);
+    }
+    return statements;
+}
+
+/// Runs `statements` in order, each with the `parameters` it uses. Gives the
+/// report and the result of the last statement that ran, which is an error
+/// when the script stopped on one.
+pub fn run_script(
+    connection: &Connection,
+    statements: &[String],
+    options: ScriptOptions,
+    parameters: &[(String, Value)],
+    readonly: bool,
+) -> (ScriptReport, Result<SqliteQueryResult, AppError>) {
+    let mut report = ScriptReport {
+        statements: Vec::new(),
+        skipped: 0,
+        rolled_back: false,
+    };
+    if options.single_transaction {
+        let savepoint = format!("SAVEPOINT {}", SCRIPT_SAVEPOINT);
+        if let Err(e) = connection.execute_batch(&savepoint) {
+            report.skipped = statements.len();
+            return (report, Err(e.into()));
+        }
+    }
+
+    let mut last = Ok(SqliteQueryResult::mutated(0, QueryStats::default()));
+    let mut stopped = false;
+    for (index, sql) in statements.iter().enumerate() {
+        let start = Instant::now();
+        let result = run_statement(connection, sql, parameters, readonly);
+        report.statements.push(StatementReport {
+            sql: sql.clone(),
+            result: match &result {
+                Ok(result) => Ok(result.summary()),
+                Err(e) => Err(e.to_string()),
+            },
+            duration: start.elapsed(),
+        });
+        // a cancelled script always stops
+        stopped = matches!(result, Err(AppError::INTERRUPTED))
+            || (result.is_err() && options.stop_on_error);
+        last = result;
+        if stopped {
+            report.skipped = statements.len() - index - 1;
+            break;
+        }
+    }
+
+    if options.single_transaction {
+        let end = if stopped {
+            format!("ROLLBACK TO {0}; RELEASE {0}", SCRIPT_SAVEPOINT)
+        } else {
+            format!("RELEASE {}", SCRIPT_SAVEPOINT)
+        };
+        let start = Instant::now();
+        match connection.execute_batch(&end) {
+            Ok(()) => report.rolled_back = stopped,
+            // e.g. the script committed or rolled back itself
+            Err(e) => report.statements.push(StatementReport {
+                sql: end,
+                result: Err(e.to_string()),
+                duration: start.elapsed(),
+            }),
+        }
+    }
+    return (report, last);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_at_semicolons() {
+        assert_eq!(
+            split_statements("SELECT 1; SELECT 2;\nSELECT 3"),
+            vec!["SELECT 1", "SELECT 2", "SELECT 3"]
+        );
+        assert_eq!(split_statements("SELECT 1;"), vec!["SELECT 1"]);
+        assert!(split_statements("").is_empty());
+    }
+
+    #[test]
+    fn ignores_semicolons_in_strings_and_identifiers() {
+        assert_eq!(
+            split_statements("INSERT INTO t VALUES ('a;b'); SELECT \"x;y\" FROM t"),
+            vec!["INSERT INTO t VALUES ('a;b')", "SELECT \"x;y\" FROM t"]
+        );
+        assert_eq!(
+            split_statements("SELECT 'it''s; fine'; SELECT 2"),
+            vec!["SELECT 'it''s; fine'", "SELECT 2"]
+        );
+    }
+
+    #[test]
+    fn ignores_semicolons_in_comments() {
+        assert_eq!(
+            split_statements("SELECT 1 -- one; two\n; /* three; */ SELECT 2"),
+            vec!["SELECT 1 -- one; two", "/* three; */ SELECT 2"]
+        );
+    }
+
+    #[test]
+    fn leaves_out_empty_pieces() {
+        assert_eq!(
+            split_statements(";; SELECT 1; -- only a comment\n;  ;"),
+            vec!["SELECT 1"]
+        );
+    }
+
+    #[test]
+    fn keeps_trigger_bodies_together() {
+        let trigger = "CREATE TRIGGER log AFTER INSERT ON t BEGIN \
+                       INSERT INTO l VALUES (CASE WHEN new.a > 0 THEN 'pos' ELSE 'neg' END); \
+                       UPDATE c SET n = n + 1; END";
+        assert_eq!(
+            split_statements(&format!("{}; SELECT 1", trigger)),
+            vec![trigger, "SELECT 1"]
+        );
+        let temporary =
+            "CREATE TEMP TRIGGER IF NOT EXISTS x BEFORE DELETE ON t BEGIN SELECT 1; END";
+        assert_eq!(split_statements(temporary), vec![temporary]);
+    }
+
+    #[test]
+    fn slices_after_multibyte_characters() {
+        assert_eq!(
+            split_statements("SELECT 'ünï'; SELECT 'cödé'"),
+            vec!["SELECT 'ünï'", "SELECT 'cödé'"]
+        );
+    }
+
+    #[test]
+    fn keeps_input_the_tokenizer_rejects() {
+        assert_eq!(
+            split_statements(" SELECT 'open; SELECT 2 "),
+            vec!["SELECT 'open; SELECT 2"]
+        );
+    }
+
+    fn script(
+        sql: &str,
+        stop_on_error: bool,
+        single_transaction: bool,
+    ) -> (Connection, ScriptReport) {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch("CREATE TABLE t (a UNIQUE)")
+            .unwrap();
+        let options = ScriptOptions {
+            stop_on_error,
+            single_transaction,
+        };
+        let (report, _) = run_script(&connection, &split_statements(sql), options, &[], false);
+        return (connection, report);
+    }
+
+    fn values(connection: &Connection) -> Vec<i64> {
+        return connection
+            .prepare("SELECT a FROM t ORDER BY a")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+    }
+
+    const FAILING: &str =
+        "INSERT INTO t VALUES (1); INSERT INTO t VALUES (1); INSERT INTO t VALUES (2)";
+
+    #[test]
+    fn stops_at_the_first_error() {
+        let (connection, report) = script(FAILING, true, false);
+        assert_eq!(report.statements.len(), 2);
+        assert_eq!(report.failed(), 1);
+        assert_eq!(report.skipped, 1);
+        assert!(!report.rolled_back);
+        assert_eq!(values(&connection), vec![1]);
+    }
+
+    #[test]
+    fn keeps_going_after_an_error() {
+        let (connection, report) = script(FAILING, false, false);
+        assert_eq!(report.statements.len(), 3);
+        assert_eq!(report.failed(), 1);
+        assert_eq!(report.skipped, 0);
+        assert_eq!(values(&connection), vec![1, 2]);
+    }
+
+    #[test]
+    fn rolls_back_a_single_transaction_that_stopped() {
+        let (connection, report) = script(FAILING, true, true);
+        assert_eq!(report.skipped, 1);
+        assert!(report.rolled_back);
+        assert!(values(&connection).is_empty());
+        assert!(connection.is_autocommit());
+
+        let (connection, report) = script(
+            "INSERT INTO t VALUES (1); INSERT INTO t VALUES (2)",
+            true,
+            true,
+        );
+        assert_eq!(report.failed(), 0);
+        assert!(!report.rolled_back);
+        assert_eq!(values(&connection), vec![1, 2]);
+        assert!(connection.is_autocommit());
+    }
+}
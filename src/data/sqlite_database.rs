@@ -1,113 +1,247 @@
-use std::{any::Any, ops::Deref, path::PathBuf, rc::Rc};
+use std::{cell::Cell, collections::BTreeSet, io, path::PathBuf, rc::Rc, time::Instant};
 
 use num_enum::TryFromPrimitive;
-use ratatui::crossterm::event::{KeyCode, KeyEvent};
-use rusqlite::{Connection, Error};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use rusqlite::{Connection, Error, OpenFlags};
 use strum::EnumCount;
 
-use super::{sqlite_query::SqliteQueryResult, sqlite_table::SqliteTable};
+use super::{
+    cell_editor::{CellEditor, EditTarget, PendingEdit},
+    change_detection::data_version,
+    completion::CompletionPopup,
+    object_tree::ObjectTree,
+    query_editor::PAGE_LINES,
+    query_history::QueryHistory,
+    query_parameters::{ParameterDialog, ParameterMemory},
+    query_plan::QueryPlan,
+    query_worker::{QueryWorker, RunningQuery},
+    result_export::ExportDialog,
+    row_editor::{DeleteConfirmation, RowForm},
+    script::{ScriptOptions, ScriptReport},
+    sqlite_query::SqliteQueryResult,
+    sqlite_table::{SqliteTable, TableSchema},
+    table_builder::{TableBuilder, TableBuilderFocus},
+    table_import::ImportDialog,
+};
+use crate::error::AppError;
 #[derive(Clone)]
 pub struct SqliteDatabase {
-    connection: Rc<Connection>,
+    pub(crate) connection: Rc<Connection>,
     pub database_path: PathBuf,
+    pub readonly: bool,
 }
 impl SqliteDatabase {
-    pub fn new(connection: Connection, database_path: PathBuf) -> Self {
-        return Self {
+    /// Opens the database file. Unless `create` is set a missing file is an
+    /// error instead of silently becoming a new empty database.
+    pub fn open(database_path: PathBuf, readonly: bool, create: bool) -> Result<Self, AppError> {
+        let flags = if readonly {
+            OpenFlags::SQLITE_OPEN_READ_ONLY
+        } else if create {
+            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE
+        } else {
+            OpenFlags::SQLITE_OPEN_READ_WRITE
+        };
+        if !create && !database_path.exists() {
+            return Err(AppError::IO(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "database file {:?} does not exist (pass --create to create it)",
+                    database_path
+                ),
+            )));
+        }
+        let connection = Connection::open_with_flags(&database_path, flags | extra_flags())?;
+        return Ok(Self {
             connection: Rc::new(connection),
             database_path,
+            readonly,
+        });
+    }
+    /// Another connection to the same database, e.g. for the query worker.
+    pub fn open_connection(&self) -> Result<Connection, Error> {
+        let flags = if self.readonly {
+            OpenFlags::SQLITE_OPEN_READ_ONLY
+        } else {
+            OpenFlags::SQLITE_OPEN_READ_WRITE
         };
+        return Connection::open_with_flags(&self.database_path, flags | extra_flags());
     }
-    pub fn tables(&self) -> Vec<SqliteTable> {
+    pub fn tables(&self) -> Result<Vec<SqliteTable>, Error> {
         let mut tables = Vec::new();
 
         // Query all tables
-        let mut stmt = self
-            .connection
-            .prepare(
-                "SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%';",
-            )
-            .unwrap();
+        let mut stmt = self.connection.prepare(
+            "SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%';",
+        )?;
 
         let rows: Vec<String> = stmt
-            .query_map([], |r| r.get::<usize, String>(0))
-            .unwrap()
-            .map(|r| r.unwrap())
-            .collect();
+            .query_map([], |r| r.get::<usize, String>(0))?
+            .collect::<Result<_, _>>()?;
         for row in rows {
             tables.push(SqliteTable::new(row.clone(), self.connection.clone()));
         }
-        return tables;
+        return Ok(tables);
     }
 }
 
+fn extra_flags() -> OpenFlags {
+    return OpenFlags::SQLITE_OPEN_URI | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
 pub enum SqliteDatabaseStateMode {
     TABLE_SELECTION,
     TABLE_OPTION_SELECTION,
     QUERY_TOOL,
+    RESULT_NAVIGATION,
+    HISTORY_SEARCH,
+    TABLE_CREATION,
+    SCHEMA_VIEW,
+    ROW_INSERT,
+    QUERY_PLAN,
+    PARAMETERS,
+    EXPORT,
+    IMPORT,
 }
 #[repr(usize)]
 #[derive(EnumCount, TryFromPrimitive)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum TableOption {
     CREATE = 0,
-    CUSTOM = 1,
+    IMPORT = 1,
+    CUSTOM = 2,
 }
 pub struct SqliteDatabaseState {
-    connection: Rc<Connection>,
+    pub(crate) database: SqliteDatabase,
     pub tables: Vec<SqliteTable>,
     pub queried_table_state: Option<SqliteQueryResult>,
     pub current_query: String,
     pub current_query_cursor: (usize, usize),
-    pub selected_table: Option<usize>,
+    pub result_cursor: (usize, usize),
+    pub result_scroll: (usize, usize),
+    /// Rows and columns that fit in the result grid, written on every render.
+    pub result_viewport: Cell<(usize, usize)>,
+    pub object_tree: ObjectTree,
     pub selected_table_option: Option<usize>,
-    pub error: Option<Error>,
+    pub error: Option<AppError>,
     pub mode: SqliteDatabaseStateMode,
+    pub table_builder: Option<TableBuilder>,
+    pub history: QueryHistory,
+    /// How far back Ctrl+P has gone, `None` while editing a new query.
+    pub history_position: Option<usize>,
+    pub history_draft: String,
+    pub history_search: String,
+    pub history_selected: usize,
+    pub completion: Option<CompletionPopup>,
+    pub edit_target: Option<EditTarget>,
+    pub pending_edits: Vec<PendingEdit>,
+    pub cell_editor: Option<CellEditor>,
+    /// Problems with editing are shown in the grid so the result stays visible.
+    pub edit_error: Option<AppError>,
+    pub row_form: Option<RowForm>,
+    /// Result rows marked with Space for deletion.
+    pub marked_rows: BTreeSet<usize>,
+    pub delete_confirmation: Option<DeleteConfirmation>,
+    pub schema: Option<TableSchema>,
+    pub schema_scroll: usize,
+    /// Furthest the schema view can scroll, written on every render.
+    pub schema_max_scroll: Cell<usize>,
+    pub query_plan: Option<QueryPlan>,
+    pub worker: Option<QueryWorker>,
+    pub running_query: Option<RunningQuery>,
+    /// Set once a query attached a database, created a temporary object or
+    /// changed a pragma. The worker's connection doesn't have that state, so
+    /// from then on every query runs on the UI's connection.
+    pub connection_state_changed: bool,
+    /// `PRAGMA data_version` when changes were last looked for.
+    pub data_version: i64,
+    /// When a commit by another connection or process was noticed.
+    pub external_change: Option<Instant>,
+    pub script_options: ScriptOptions,
+    /// Per statement results when the last query was a script.
+    pub script_report: Option<ScriptReport>,
+    /// Savepoints set with F7 in the open transaction, newest last.
+    pub savepoints: Vec<String>,
+    pub parameter_dialog: Option<ParameterDialog>,
+    pub parameter_memory: ParameterMemory,
+    pub export_dialog: Option<ExportDialog>,
+    /// Where the last export went, shown under the result.
+    pub export_notice: Option<String>,
+    pub import_dialog: Option<ImportDialog>,
 }
 impl SqliteDatabaseState {
+    /// Errors reading the schema are kept in `error` so the UI still opens.
     pub fn new(database: &SqliteDatabase) -> Self {
-        return Self {
-            connection: database.connection.clone(),
-            tables: database.tables(),
+        let mut state = Self {
+            database: database.clone(),
+            tables: Vec::new(),
             queried_table_state: None,
             current_query: String::new(),
             current_query_cursor: (0, 0),
-            selected_table: None,
+            result_cursor: (0, 0),
+            result_scroll: (0, 0),
+            result_viewport: Cell::new((0, 0)),
+            object_tree: ObjectTree::default(),
             selected_table_option: None,
             mode: SqliteDatabaseStateMode::TABLE_SELECTION,
             error: None,
+            table_builder: None,
+            history: QueryHistory::in_memory(),
+            history_position: None,
+            history_draft: String::new(),
+            history_search: String::new(),
+            history_selected: 0,
+            completion: None,
+            edit_target: None,
+            pending_edits: Vec::new(),
+            cell_editor: None,
+            edit_error: None,
+            row_form: None,
+            marked_rows: BTreeSet::new(),
+            delete_confirmation: None,
+            schema: None,
+            schema_scroll: 0,
+            schema_max_scroll: Cell::new(0),
+            query_plan: None,
+            worker: None,
+            running_query: None,
+            connection_state_changed: false,
+            data_version: 0,
+            external_change: None,
+            script_options: ScriptOptions::default(),
+            script_report: None,
+            savepoints: Vec::new(),
+            parameter_dialog: None,
+            parameter_memory: ParameterMemory::in_memory(),
+            export_dialog: None,
+            export_notice: None,
+            import_dialog: None,
         };
+        state.reload_schema();
+        state.data_version = data_version(&database.connection).unwrap_or(0);
+        return state;
     }
 
     pub fn read_keys(&mut self, event: &KeyEvent) {
+        if self.read_running_query_keys(event)
+            || self.read_change_keys(event)
+            || self.read_transaction_keys(event)
+        {
+            return;
+        }
         match self.mode {
             SqliteDatabaseStateMode::TABLE_SELECTION => match event.code {
-                KeyCode::Up => {
-                    if let None = self.selected_table {
-                        self.selected_table = Some(0)
-                    } else if let Some(current_selected) = self.selected_table {
-                        self.selected_table = Some(wrap_in_range(
-                            current_selected.wrapping_sub(1),
-                            0,
-                            self.tables.len() - 1,
-                        ));
-                    }
-                }
-                KeyCode::Down => {
-                    if let None = self.selected_table {
-                        self.selected_table = Some(0)
-                    } else if let Some(current_selected) = self.selected_table {
-                        self.selected_table = Some(wrap_in_range(
-                            current_selected.wrapping_add(1),
-                            0,
-                            self.tables.len() - 1,
-                        ));
-                    }
-                }
-                KeyCode::Enter => {
-                    if let Some(table_index) = self.selected_table {
-                        self.select_table();
-                    }
+                KeyCode::Up => self.object_tree.move_selection(-1),
+                KeyCode::Down => self.object_tree.move_selection(1),
+                KeyCode::Right => self.object_tree.expand_selected(),
+                KeyCode::Left => self.object_tree.collapse_selected(),
+                KeyCode::Enter if self.object_tree.selected_object().is_some() => {
+                    self.select_table()
                 }
+                KeyCode::Enter => self.object_tree.toggle_selected(),
+                KeyCode::Char('i') => self.open_schema_view(),
+                KeyCode::Insert => self.open_row_form(),
                 KeyCode::Tab => {
                     self.mode = SqliteDatabaseStateMode::TABLE_OPTION_SELECTION;
                     self.selected_table_option = Some(0);
@@ -116,41 +250,84 @@ impl SqliteDatabaseState {
                 _ => (),
             },
             SqliteDatabaseStateMode::QUERY_TOOL => {
+                if self.read_completion_keys(event) {
+                    return;
+                }
+                let control = event.modifiers.contains(KeyModifiers::CONTROL);
+                let completing = self.completion.take().is_some();
                 match event.code {
+                    KeyCode::F(5) => self.execute(),
+                    KeyCode::Char('p') if control => self.recall_previous(),
+                    KeyCode::Char('n') if control => self.recall_next(),
+                    KeyCode::Char('r') if control => self.open_history_search(),
+                    KeyCode::F(4) => self.open_query_plan(),
+                    KeyCode::Char('e') if control => {
+                        self.script_options.stop_on_error = !self.script_options.stop_on_error
+                    }
+                    KeyCode::Char('t') if control => {
+                        self.script_options.single_transaction =
+                            !self.script_options.single_transaction
+                    }
+                    KeyCode::F(6) => self.mode = SqliteDatabaseStateMode::RESULT_NAVIGATION,
+                    KeyCode::Enter if control => self.execute(),
+                    KeyCode::Enter => self.insert_char('\n'),
                     KeyCode::Backspace => {
-                        if self.current_query.chars().count() == 0 {
-                            return;
+                        self.delete_backward();
+                        if completing {
+                            self.update_completion(false);
                         }
-                        self.current_query
-                            .remove(self.current_query_cursor.0.saturating_sub(1));
-                        self.current_query_cursor.0 = self.current_query_cursor.0.saturating_sub(1);
-                    }
-                    KeyCode::Enter => {
-                        //execute
-                        self.execute();
                     }
-                    KeyCode::Char(c) => {
-                        self.current_query.insert(self.current_query_cursor.0, c);
-                        self.current_query_cursor.0 += 1;
+                    KeyCode::Delete => self.delete_forward(),
+                    KeyCode::Tab => self.insert_str("    "),
+                    KeyCode::Char(' ') if control => self.update_completion(true),
+                    KeyCode::Char(c)
+                        if !event
+                            .modifiers
+                            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+                    {
+                        self.insert_char(c);
+                        if c.is_alphanumeric() || c == '_' {
+                            self.update_completion(false);
+                        } else if c == '.' {
+                            self.update_completion(true);
+                        }
                     }
                     KeyCode::Esc => {
                         self.mode = SqliteDatabaseStateMode::TABLE_SELECTION;
                         self.selected_table_option = None
                     }
-                    KeyCode::Left => {
-                        self.current_query_cursor.0 = self
-                            .current_query_cursor
-                            .0
-                            .saturating_sub(1)
-                            .clamp(0, self.current_query.chars().count())
-                    }
-                    KeyCode::Right => {
-                        self.current_query_cursor.0 = self
-                            .current_query_cursor
-                            .0
-                            .saturating_add(1)
-                            .clamp(0, self.current_query.chars().count())
-                    }
+                    KeyCode::Left if control => self.move_word_left(),
+                    KeyCode::Right if control => self.move_word_right(),
+                    KeyCode::Left => self.move_left(),
+                    KeyCode::Right => self.move_right(),
+                    KeyCode::Up => self.move_up(1),
+                    KeyCode::Down => self.move_down(1),
+                    KeyCode::PageUp => self.move_up(PAGE_LINES),
+                    KeyCode::PageDown => self.move_down(PAGE_LINES),
+                    KeyCode::Home => self.move_home(),
+                    KeyCode::End => self.move_end(),
+                    _ => (),
+                }
+            }
+            SqliteDatabaseStateMode::RESULT_NAVIGATION => {
+                self.export_notice = None;
+                if self.read_row_keys(event) || self.read_cell_edit_keys(event) {
+                    return;
+                }
+                let control = event.modifiers.contains(KeyModifiers::CONTROL);
+                match event.code {
+                    KeyCode::Esc | KeyCode::F(6) => self.mode = SqliteDatabaseStateMode::QUERY_TOOL,
+                    KeyCode::Char('e') if control => self.open_export_dialog(),
+                    KeyCode::Up => self.move_result_cursor(-1, 0),
+                    KeyCode::Down => self.move_result_cursor(1, 0),
+                    KeyCode::Left => self.move_result_cursor(0, -1),
+                    KeyCode::Right => self.move_result_cursor(0, 1),
+                    KeyCode::PageUp => self.move_result_cursor(-self.result_page_size(), 0),
+                    KeyCode::PageDown => self.move_result_cursor(self.result_page_size(), 0),
+                    KeyCode::Home if control => self.move_result_cursor_to(Some(0), None),
+                    KeyCode::End if control => self.move_result_cursor_to_end(),
+                    KeyCode::Home => self.move_result_cursor_to(None, Some(0)),
+                    KeyCode::End => self.move_result_cursor_to(None, Some(usize::MAX)),
                     _ => (),
                 }
             }
@@ -171,75 +348,182 @@ impl SqliteDatabaseState {
                     }
                 }
                 KeyCode::Enter => {
-                    if let Some(option) = self.selected_table_option {
-                        match TableOption::try_from(option) {
-                            Ok(option) => match option {
-                                TableOption::CREATE => (),
-                                TableOption::CUSTOM => {
-                                    self.selected_table = None;
-                                    self.select_table();
-                                }
-                                _ => (),
-                            },
-                            Err(_) => (),
+                    if let Some(option) = self.selected_table_option
+                        && let Ok(option) = TableOption::try_from(option)
+                    {
+                        match option {
+                            TableOption::CREATE => self.open_table_builder(),
+                            TableOption::IMPORT => self.open_import_dialog(),
+                            TableOption::CUSTOM => {
+                                self.object_tree.selected = None;
+                                self.select_table();
+                            }
                         }
                     }
                 }
                 _ => (),
             },
+            SqliteDatabaseStateMode::HISTORY_SEARCH => self.read_history_search_keys(event),
+            SqliteDatabaseStateMode::TABLE_CREATION => self.read_table_builder_keys(event),
+            SqliteDatabaseStateMode::ROW_INSERT => self.read_row_form_keys(event),
+            SqliteDatabaseStateMode::QUERY_PLAN => self.read_query_plan_keys(event),
+            SqliteDatabaseStateMode::PARAMETERS => self.read_parameter_keys(event),
+            SqliteDatabaseStateMode::EXPORT => self.read_export_keys(event),
+            SqliteDatabaseStateMode::IMPORT => self.read_import_keys(event),
+            SqliteDatabaseStateMode::SCHEMA_VIEW => match event.code {
+                KeyCode::Esc | KeyCode::Char('i') => {
+                    self.schema = None;
+                    self.mode = SqliteDatabaseStateMode::TABLE_SELECTION;
+                }
+                KeyCode::Enter => {
+                    self.schema = None;
+                    self.select_table();
+                }
+                KeyCode::Up => self.schema_scroll = self.schema_scroll.saturating_sub(1),
+                KeyCode::Down => {
+                    self.schema_scroll = (self.schema_scroll + 1).min(self.schema_max_scroll.get())
+                }
+                KeyCode::PageUp => {
+                    self.schema_scroll = self.schema_scroll.saturating_sub(PAGE_LINES)
+                }
+                KeyCode::PageDown => {
+                    self.schema_scroll =
+                        (self.schema_scroll + PAGE_LINES).min(self.schema_max_scroll.get())
+                }
+                KeyCode::Home => self.schema_scroll = 0,
+                KeyCode::End => self.schema_scroll = self.schema_max_scroll.get(),
+                _ => (),
+            },
+        }
+    }
+
+    fn read_table_builder_keys(&mut self, event: &KeyEvent) {
+        let Some(builder) = &mut self.table_builder else {
+            self.mode = SqliteDatabaseStateMode::TABLE_SELECTION;
+            return;
+        };
+        let control = event.modifiers.contains(KeyModifiers::CONTROL);
+        match event.code {
+            KeyCode::Esc => {
+                self.table_builder = None;
+                self.error = None;
+                self.mode = SqliteDatabaseStateMode::TABLE_OPTION_SELECTION;
+            }
+            KeyCode::Enter => self.create_table(),
+            KeyCode::Tab => builder.focus_next_field(),
+            KeyCode::BackTab => builder.focus_previous_field(),
+            KeyCode::Up => builder.focus_up(),
+            KeyCode::Down => builder.focus_down(),
+            KeyCode::Char('n') if control => builder.add_column(),
+            KeyCode::Char('d') if control => builder.remove_focused_column(),
+            KeyCode::Char(' ') if matches!(builder.focus, TableBuilderFocus::Column(_, field) if field.is_toggle()) => {
+                builder.toggle_focused()
+            }
+            KeyCode::Char(c)
+                if !event
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                if let Some(text) = builder.focused_text_mut() {
+                    text.push(c);
+                }
+            }
+            KeyCode::Backspace => {
+                if let Some(text) = builder.focused_text_mut() {
+                    text.pop();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Opens the table wizard, refused on a read-only database.
+    pub fn open_table_builder(&mut self) {
+        if self.database.readonly {
+            self.error = Some(read_only_error());
+            return;
         }
+        self.error = None;
+        self.table_builder = Some(TableBuilder::new());
+        self.mode = SqliteDatabaseStateMode::TABLE_CREATION;
     }
 
-    pub fn execute(&mut self) {
+    /// Runs the statement built by the table wizard and reloads the table list
+    /// so the new table can be selected straight away.
+    pub fn create_table(&mut self) {
+        let Some(builder) = &self.table_builder else {
+            return;
+        };
         self.error = None;
-        let stmt = self.connection.prepare(&self.current_query);
-        if let Err(e) = stmt {
+        if self.database.readonly {
+            self.error = Some(read_only_error());
+            return;
+        }
+        if let Err(e) = builder.create(&self.database.connection) {
             self.error = Some(e);
             return;
         }
-        let mut stmt = stmt.unwrap();
+        let name = builder.name.clone();
+        self.table_builder = None;
+        self.reload_schema();
+        self.object_tree.select_object("main", &name);
+        self.selected_table_option = None;
+        self.mode = SqliteDatabaseStateMode::TABLE_SELECTION;
+    }
 
-        if stmt.readonly() {
-            let column_names = stmt
-                .column_names()
-                .iter()
-                .map(|s| s.deref().to_owned())
-                .collect();
-            let rows = stmt.query([]);
-            if let Ok(rows) = rows {
-                self.queried_table_state = Some(SqliteQueryResult::new(rows, column_names));
-            } else if let Err(e) = rows {
-                self.error = Some(e);
+    /// Opens the query tool, querying the selected table or view. Selecting
+    /// an index or trigger shows the schema of its table instead.
+    pub fn select_table(&mut self) {
+        match self.object_tree.selected_object() {
+            Some(object) if object.is_queryable() => {
+                // tables without a primary key are edited by a hidden rowid,
+                // see `with_rowid_key`
+                let query = format!("SELECT * FROM {}", object.qualified_name());
+                self.mode = SqliteDatabaseStateMode::QUERY_TOOL;
+                self.set_query(query);
+                self.execute();
             }
-        } else {
-            let rows_changed = stmt.execute([]);
-            if let Ok(rows_changed) = rows_changed {
-                self.queried_table_state = Some(SqliteQueryResult::mutated(rows_changed));
-            } else if let Err(e) = rows_changed {
-                self.error = Some(e);
+            Some(_) => self.open_schema_view(),
+            None => {
+                self.mode = SqliteDatabaseStateMode::QUERY_TOOL;
+                self.current_query.clear();
+                self.current_query_cursor = (0, 0);
             }
         }
     }
-    pub fn select_table(&mut self) {
-        if self.tables.len() == 0 {
+    /// Reads the schema of the highlighted table or view and shows it.
+    pub fn open_schema_view(&mut self) {
+        let Some(table) = self.object_tree.selected_table(&self.database.connection) else {
             return;
-        }
-        self.mode = SqliteDatabaseStateMode::QUERY_TOOL;
-
-        if let Some(selected) = self.selected_table {
-            self.current_query = format!("SELECT * FROM {}", self.tables[selected].name);
-            self.current_query_cursor = (self.current_query.chars().count(), 0);
-            self.execute();
-        } else {
-            self.current_query.clear();
-            self.current_query_cursor = (0, 0);
+        };
+        match table.schema() {
+            Ok(schema) => {
+                self.error = None;
+                self.schema = Some(schema);
+                self.schema_scroll = 0;
+                self.mode = SqliteDatabaseStateMode::SCHEMA_VIEW;
+            }
+            Err(e) => self.error = Some(e.into()),
         }
     }
-    pub fn sync(&mut self, database: &SqliteDatabase) {
-        self.tables = database.tables();
+    /// Reads the tables and the object tree again. A failure leaves the old
+    /// ones in place and shows the error.
+    pub fn reload_schema(&mut self) {
+        match self.database.tables() {
+            Ok(tables) => self.tables = tables,
+            Err(e) => self.error = Some(e.into()),
+        }
+        if let Err(e) = self.object_tree.reload(&self.database.connection) {
+            self.error = Some(e.into());
+        }
     }
 }
-fn wrap_in_range(x: usize, min: usize, max: usize) -> usize {
-    let range = max - min + 1;
-    (((x - min) % range + range) % range) + min
+pub(crate) fn read_only_error() -> AppError {
+    return AppError::UI(
+        "the database was opened with --readonly, statements that write are refused".to_string(),
+    );
+}
+
+pub(crate) fn pending_edits_error() -> AppError {
+    return AppError::UI("commit (Ctrl+S) or discard (Ctrl+Z) the pending edits first".to_string());
 }
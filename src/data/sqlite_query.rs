@@ -1,44 +1,439 @@
-use std::ops::Deref;
+use std::time::{Duration, Instant};
 
-use indexmap::IndexMap;
-use rusqlite::{Row, Rows, types::Value};
+use rusqlite::{Connection, Error, Statement, StatementStatus, params_from_iter, types::Value};
+use sqlparser::{
+    dialect::SQLiteDialect,
+    keywords::Keyword,
+    tokenizer::{Token, Tokenizer},
+};
+
+/// Number of rows fetched from the database at once.
+pub const PAGE_SIZE: usize = 500;
+
+/// How pages of a read-only statement are fetched again after the first one.
+enum Pagination {
+    /// The statement is wrapped in `SELECT * FROM (...) LIMIT ? OFFSET ?`,
+    /// the two parameters come after the statement's own ones.
+    LimitOffset(String),
+    /// Statements that can't be used as a subquery (e.g. `PRAGMA`) are run
+    /// again and stepped past the rows before the page. So are statements
+    /// ending in `ORDER BY`: SQL doesn't promise that a subquery's order
+    /// survives the outer query.
+    Rescan,
+}
+
+/// Counting the rows of a result, apart from the result so it can run on
+/// the worker's connection.
+pub struct RowCount {
+    pub sql: String,
+    pub params: Vec<Value>,
+    /// Whether `sql` can be wrapped in `SELECT COUNT(*) FROM (...)`.
+    subquery: bool,
+}
+impl RowCount {
+    pub fn run(&self, connection: &Connection) -> Result<usize, Error> {
+        if self.subquery {
+            let trimmed = self.sql.trim().trim_end_matches(';');
+            return connection.query_row(
+                &format!("SELECT COUNT(*) FROM (\n{}\n)", trimmed),
+                params_from_iter(&self.params),
+                |row| row.get::<usize, usize>(0),
+            );
+        }
+        let mut stmt = connection.prepare(&self.sql)?;
+        let mut rows = stmt.query(params_from_iter(&self.params))?;
+        let mut count = 0;
+        while rows.next()?.is_some() {
+            count += 1;
+        }
+        return Ok(count);
+    }
+}
+
+/// Counters SQLite keeps per statement, see
+/// https://www.sqlite.org/c3ref/c_stmtstatus_counter.html
+#[derive(Clone, Copy, Default, Debug)]
+pub struct StatementCounters {
+    /// Rows stepped over in full table scans, a hint that an index is missing.
+    pub fullscan_steps: i32,
+    pub sorts: i32,
+    /// Indexes built on the fly because no suitable one exists.
+    pub autoindexes: i32,
+    pub vm_steps: i32,
+}
+impl StatementCounters {
+    pub fn read(stmt: &Statement) -> Self {
+        return Self {
+            fullscan_steps: stmt.get_status(StatementStatus::FullscanStep),
+            sorts: stmt.get_status(StatementStatus::Sort),
+            autoindexes: stmt.get_status(StatementStatus::AutoIndex),
+            vm_steps: stmt.get_status(StatementStatus::VmStep),
+        };
+    }
+}
+
+/// How the execution of a statement went, shown under its result. For
+/// read-only statements this covers fetching the first page.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct QueryStats {
+    pub duration: Duration,
+    pub counters: StatementCounters,
+    /// Set when the statement inserted a row.
+    pub last_insert_rowid: Option<i64>,
+}
 
 pub struct SqliteQueryResult {
-    pub rows: Vec<IndexMap<String, Value>>,
+    pub sql: String,
+    pub columns: Vec<String>,
+    /// Trailing columns fetched to find rows again but not shown, like the
+    /// rowid `with_rowid_key` adds.
+    pub hidden_columns: usize,
+    /// The cached page, starting at row `rows_offset` of the full result.
+    pub rows: Vec<Vec<Value>>,
+    pub rows_offset: usize,
+    /// Total row count, known once the last page has been fetched or the rows were counted.
+    pub total_rows: Option<usize>,
+    /// Highest row count seen so far while the total is still unknown.
+    rows_seen: usize,
+    pagination: Pagination,
     pub rows_affected: Option<usize>,
+    pub stats: QueryStats,
+    /// Values bound to the statement's parameters, by position.
+    pub params: Vec<Value>,
 }
 impl SqliteQueryResult {
-    pub fn new(mut rows: Rows, column_names: Vec<String>) -> Self {
-        let mut result = Vec::new();
-        let column_count = column_names.len();
-        while let Ok(Some(row)) = rows.next() {
-            let mut map = IndexMap::new();
-            for col in 0..column_count {
-                map.insert(column_names[col].clone(), row.get(col).unwrap());
-            }
-            result.push(map);
-        }
-
-        return SqliteQueryResult {
-            rows: result,
+    /// Runs a read-only statement with `params` bound and loads its first page.
+    pub fn query(
+        connection: &Connection,
+        sql: &str,
+        columns: Vec<String>,
+        params: Vec<Value>,
+    ) -> Result<Self, Error> {
+        let start = Instant::now();
+        let trimmed = sql.trim().trim_end_matches(';');
+        let wrapped = format!("SELECT * FROM (\n{}\n) LIMIT ? OFFSET ?", trimmed);
+        let pagination = match connection.prepare(&wrapped) {
+            Ok(_) if !has_order_by(trimmed) => Pagination::LimitOffset(wrapped),
+            _ => Pagination::Rescan,
+        };
+        let mut result = Self {
+            sql: sql.to_string(),
+            columns,
+            hidden_columns: 0,
+            rows: Vec::new(),
+            rows_offset: 0,
+            total_rows: None,
+            rows_seen: 0,
+            pagination,
             rows_affected: None,
+            stats: QueryStats::default(),
+            params,
         };
+        result.stats.counters = result.load_page(connection, 0)?;
+        result.stats.duration = start.elapsed();
+        return Ok(result);
     }
-    pub fn mutated(rows_affected: usize) -> Self {
+    pub fn mutated(rows_affected: usize, stats: QueryStats) -> Self {
         return Self {
+            sql: String::new(),
+            columns: Vec::new(),
+            hidden_columns: 0,
             rows: Vec::new(),
+            rows_offset: 0,
+            total_rows: Some(0),
+            rows_seen: 0,
+            pagination: Pagination::Rescan,
             rows_affected: Some(rows_affected),
+            stats,
+            params: Vec::new(),
         };
     }
     pub fn is_readonly(&self) -> bool {
         return self.rows_affected.is_none();
     }
-    pub fn window(&self, offset: usize, max_length: u8) -> &[IndexMap<String, Value>] {
-        let max_window_size = self.rows.len() - offset;
-        if (max_length as usize) < max_window_size {
-            return &self.rows[offset..offset + max_length as usize];
+    /// Rows known to exist: the total once it is known, otherwise the rows seen so far.
+    pub fn row_count(&self) -> usize {
+        return self.total_rows.unwrap_or(self.rows_seen);
+    }
+    pub fn is_complete(&self) -> bool {
+        return self.total_rows.is_some();
+    }
+    /// Rows changed or returned, e.g. `3 rows changed` or `500+ rows`.
+    pub fn summary(&self) -> String {
+        return match self.rows_affected {
+            Some(affected) => format!("{} rows changed", affected),
+            None if self.is_complete() => format!("{} rows", self.row_count()),
+            None => format!("{}+ rows", self.row_count()),
+        };
+    }
+    /// The columns that are shown, without the hidden ones.
+    pub fn column_names(&self) -> &[String] {
+        return &self.columns[..self.columns.len() - self.hidden_columns];
+    }
+
+    /// Cached rows in `offset..offset + max_length`. Call `ensure_window`
+    /// first, rows outside the cached page are left out.
+    pub fn window(&self, offset: usize, max_length: u8) -> &[Vec<Value>] {
+        if offset < self.rows_offset {
+            return &[];
+        }
+        let start = (offset - self.rows_offset).min(self.rows.len());
+        let end = (start + max_length as usize).min(self.rows.len());
+        return &self.rows[start..end];
+    }
+
+    /// Makes sure `offset..offset + length` is cached, fetching a new page
+    /// around it when it isn't.
+    pub fn ensure_window(
+        &mut self,
+        connection: &Connection,
+        offset: usize,
+        length: usize,
+    ) -> Result<(), Error> {
+        if !self.is_readonly() {
+            return Ok(());
+        }
+        let cached_end = self.rows_offset + self.rows.len();
+        let wanted_end = match self.total_rows {
+            Some(total) => (offset + length).min(total),
+            None => offset + length,
+        };
+        if offset >= self.rows_offset && wanted_end <= cached_end {
+            return Ok(());
+        }
+        // keep some rows above the requested window so scrolling back up
+        // doesn't immediately need another page
+        let page_offset = offset.saturating_sub(PAGE_SIZE / 4);
+        self.load_page(connection, page_offset)?;
+        return Ok(());
+    }
+
+    /// Counts every row of the result, for jumping to the end.
+    pub fn count_rows(&mut self, connection: &Connection) -> Result<usize, Error> {
+        if let Some(total) = self.total_rows {
+            return Ok(total);
+        }
+        let total = self.counting().run(connection)?;
+        self.total_rows = Some(total);
+        return Ok(total);
+    }
+
+    /// What `count_rows` runs, to count somewhere else.
+    pub fn counting(&self) -> RowCount {
+        return RowCount {
+            sql: self.sql.clone(),
+            params: self.params.clone(),
+            subquery: matches!(self.pagination, Pagination::LimitOffset(_)),
+        };
+    }
+
+    /// Takes the total counted by `count`, returns false when it was counted
+    /// for another result.
+    pub fn set_total_rows(&mut self, count: &RowCount, total: usize) -> bool {
+        if self.sql != count.sql || self.params != count.params || !self.is_readonly() {
+            return false;
+        }
+        self.total_rows = Some(total);
+        return true;
+    }
+
+    /// Calls `f` for every row of the result in order, without the hidden
+    /// columns. The statement runs once and its rows are streamed, the cached
+    /// page is left alone.
+    pub fn for_each_row<E: From<Error>>(
+        &mut self,
+        connection: &Connection,
+        mut f: impl FnMut(&[Value]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        if !self.is_readonly() {
+            return Ok(());
+        }
+        let column_count = self.column_names().len();
+        let mut stmt = connection.prepare(&self.sql)?;
+        let mut rows = stmt.query(params_from_iter(&self.params))?;
+        let mut count = 0;
+        while let Some(row) = rows.next()? {
+            f(&read_row(row, column_count)?)?;
+            count += 1;
+        }
+        self.total_rows = Some(count);
+        self.rows_seen = count;
+        return Ok(());
+    }
+
+    /// Fetches the page starting at `offset`, returns the counters of the
+    /// statement that read it.
+    fn load_page(
+        &mut self,
+        connection: &Connection,
+        offset: usize,
+    ) -> Result<StatementCounters, Error> {
+        let column_count = self.columns.len();
+        let mut page = Vec::with_capacity(PAGE_SIZE);
+        let counters = match &self.pagination {
+            Pagination::LimitOffset(wrapped) => {
+                let mut stmt = connection.prepare(wrapped)?;
+                let window = [
+                    Value::Integer(PAGE_SIZE as i64),
+                    Value::Integer(offset as i64),
+                ];
+                let mut rows = stmt.query(params_from_iter(self.params.iter().chain(&window)))?;
+                while let Some(row) = rows.next()? {
+                    page.push(read_row(row, column_count)?);
+                }
+                drop(rows);
+                StatementCounters::read(&stmt)
+            }
+            Pagination::Rescan => {
+                let mut stmt = connection.prepare(&self.sql)?;
+                let mut rows = stmt.query(params_from_iter(&self.params))?;
+                let mut index = 0;
+                while let Some(row) = rows.next()? {
+                    if index >= offset + PAGE_SIZE {
+                        break;
+                    }
+                    if index >= offset {
+                        page.push(read_row(row, column_count)?);
+                    }
+                    index += 1;
+                }
+                if index < offset + PAGE_SIZE {
+                    self.total_rows = Some(index);
+                }
+                drop(rows);
+                StatementCounters::read(&stmt)
+            }
+        };
+        if page.len() < PAGE_SIZE && self.total_rows.is_none() {
+            if page.is_empty() && offset > 0 {
+                // the page started past the end, so the end itself is still unknown
+                self.count_rows(connection)?;
+            } else {
+                self.total_rows = Some(offset + page.len());
+            }
+        }
+        self.rows_seen = self.rows_seen.max(offset + page.len());
+        self.rows_offset = offset;
+        self.rows = page;
+        return Ok(counters);
+    }
+}
+
+/// Whether `sql` ends in an `ORDER BY` of its own, not one in parentheses
+/// like a subquery's or a window's.
+fn has_order_by(sql: &str) -> bool {
+    let tokens = Tokenizer::new(&SQLiteDialect {}, sql)
+        .tokenize()
+        .unwrap_or_default();
+    let mut depth = 0;
+    return tokens.iter().any(|token| {
+        match token {
+            Token::LParen => depth += 1,
+            Token::RParen => depth -= 1,
+            Token::Word(w) if depth == 0 && w.keyword == Keyword::ORDER => return true,
+            _ => (),
+        }
+        return false;
+    });
+}
+
+fn read_row(row: &rusqlite::Row, column_count: usize) -> Result<Vec<Value>, Error> {
+    return (0..column_count).map(|i| row.get(i)).collect();
+}
+
+/// Text shown for a value in the result grid.
+pub fn display_value(value: &Value) -> String {
+    return match value {
+        Value::Null => "NULL".to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Real(f) => f.to_string(),
+        Value::Text(s) => s.to_string(),
+        Value::Blob(_) => "$$BIN$$".to_string(),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(connection: &Connection, sql: &str) -> SqliteQueryResult {
+        let columns = connection
+            .prepare(sql)
+            .unwrap()
+            .column_names()
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        return SqliteQueryResult::query(connection, sql, columns, Vec::new()).unwrap();
+    }
+
+    fn counters(connection: &Connection, sql: &str) -> StatementCounters {
+        return query(connection, sql).stats.counters;
+    }
+
+    #[test]
+    fn counters_point_at_missing_indexes() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE t (a, b);
+                 CREATE INDEX t_a ON t (a);
+                 WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 100)
+                 INSERT INTO t SELECT i, i % 10 FROM n;",
+            )
+            .unwrap();
+
+        let scan = counters(&connection, "SELECT * FROM t WHERE b = 3");
+        assert!(scan.fullscan_steps > 0);
+        assert_eq!(scan.sorts, 0);
+        assert!(scan.vm_steps > 0);
+
+        let indexed = counters(&connection, "SELECT * FROM t WHERE a = 3");
+        assert_eq!(indexed.fullscan_steps, 0);
+
+        let sorted = counters(&connection, "SELECT * FROM t ORDER BY b");
+        assert_eq!(sorted.sorts, 1);
+
+        let joined = counters(&connection, "SELECT * FROM t x JOIN t y ON x.b = y.b");
+        assert!(joined.autoindexes > 0);
+    }
+
+    #[test]
+    fn only_a_trailing_order_by_counts() {
+        assert!(has_order_by("SELECT * FROM t ORDER BY b"));
+        assert!(has_order_by(
+            "SELECT a FROM t UNION SELECT b FROM t order by 1"
+        ));
+        assert!(!has_order_by("SELECT * FROM (SELECT * FROM t ORDER BY b)"));
+        assert!(!has_order_by(
+            "SELECT row_number() OVER (ORDER BY a) FROM t"
+        ));
+        assert!(!has_order_by("SELECT 'ORDER BY' AS \"order by\""));
+    }
+
+    #[test]
+    fn rows_past_the_first_page_are_counted_and_paged() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE t (a);
+                 WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 1200)
+                 INSERT INTO t SELECT i FROM n;",
+            )
+            .unwrap();
+        for sql in ["SELECT * FROM t", "SELECT * FROM t ORDER BY a DESC"] {
+            let mut result = query(&connection, sql);
+            assert!(!result.is_complete());
+            let count = result.counting();
+            assert_eq!(count.run(&connection).unwrap(), 1200);
+            assert!(result.set_total_rows(&count, 1200));
+            assert_eq!(result.summary(), "1200 rows");
         }
 
-        return &self.rows[offset..offset + max_window_size as usize];
+        let mut ordered = query(&connection, "SELECT * FROM t ORDER BY a DESC");
+        ordered.ensure_window(&connection, 1100, 10).unwrap();
+        assert_eq!(ordered.window(1100, 1), [vec![Value::Integer(100)]]);
+        let other = query(&connection, "SELECT * FROM t").counting();
+        assert!(!ordered.set_total_rows(&other, 1200));
     }
 }
@@ -1,37 +1,53 @@
 use std::rc::Rc;
 
 use indexmap::IndexMap;
-use rusqlite::{Connection, types::Value};
+use rusqlite::{Connection, Error, OptionalExtension, types::Value};
+
+use super::identifier::quote_identifier;
 
 pub struct SqliteTable {
     pub name: String,
+    /// `main`, `temp` or the name of an attached database.
+    pub schema: String,
     connection: Rc<Connection>,
 }
 impl SqliteTable {
     pub fn new(name: String, connection: Rc<Connection>) -> Self {
-        return Self { name, connection };
+        return Self::in_schema("main".to_string(), name, connection);
+    }
+
+    pub fn in_schema(schema: String, name: String, connection: Rc<Connection>) -> Self {
+        return Self {
+            name,
+            schema,
+            connection,
+        };
+    }
+
+    /// `sqlite_master` of the table's schema.
+    fn master_table(&self) -> String {
+        return format!("{}.sqlite_master", quote_identifier(&self.schema));
     }
 
-    pub fn columns(&self) -> IndexMap<String, SqliteColumn> {
+    pub fn columns(&self) -> Result<IndexMap<String, SqliteColumn>, Error> {
         let mut map = IndexMap::new();
 
-        let column_select_query = format!("PRAGMA table_info({})", &self.name);
-
-        let mut stmt = self.connection.prepare(&column_select_query).unwrap();
-        let column_count = stmt.column_count();
-        let mut rows = stmt.query([]).unwrap();
-        while let Ok(Some(row)) = rows.next() {
-            let name: String = row.get(1).unwrap();
-            let declared_type = row.get(2).unwrap();
-            let not_null = row.get(3).unwrap();
-            let default_value = row.get(4).unwrap();
-            let pk = row.get(5).unwrap();
+        let mut stmt = self
+            .connection
+            .prepare("SELECT * FROM pragma_table_info(?1, ?2)")?;
+        let mut rows = stmt.query([&self.name, &self.schema])?;
+        while let Some(row) = rows.next()? {
+            let name: String = row.get(1)?;
+            let declared_type = row.get(2)?;
+            let not_null = row.get(3)?;
+            let default_value = row.get(4)?;
+            let pk = row.get(5)?;
             map.insert(
                 name.clone(),
                 SqliteColumn::new(name, declared_type, not_null, default_value, pk),
             );
         }
-        return map;
+        return Ok(map);
     }
 }
 
@@ -60,3 +76,154 @@ impl SqliteColumn {
         };
     }
 }
+
+/// Everything the schema view shows for one table, read once when it opens.
+pub struct TableSchema {
+    pub name: String,
+    pub schema: String,
+    pub columns: Vec<SqliteColumn>,
+    pub indexes: Vec<SqliteIndex>,
+    pub foreign_keys: Vec<SqliteForeignKey>,
+    pub triggers: Vec<SqliteTrigger>,
+    pub create_sql: Option<String>,
+    pub row_count: i64,
+}
+
+#[derive(Debug)]
+pub struct SqliteIndex {
+    pub name: String,
+    pub unique: bool,
+    /// `c` for CREATE INDEX, `u` for UNIQUE constraints and `pk` for the primary key.
+    pub origin: String,
+    pub partial: bool,
+    pub columns: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SqliteForeignKey {
+    pub id: i64,
+    pub table: String,
+    /// Pairs of (local column, referenced column). The referenced column is
+    /// `None` when the key points at the other table's primary key.
+    pub columns: Vec<(String, Option<String>)>,
+    pub on_update: String,
+    pub on_delete: String,
+}
+
+#[derive(Debug)]
+pub struct SqliteTrigger {
+    pub name: String,
+    pub sql: Option<String>,
+}
+
+/// Schema details beyond the columns. The table valued pragma functions take
+/// the name as a parameter so no quoting is needed.
+impl SqliteTable {
+    pub fn indexes(&self) -> Result<Vec<SqliteIndex>, Error> {
+        let mut stmt = self.connection.prepare(
+            "SELECT name, \"unique\", origin, partial FROM pragma_index_list(?1, ?2) ORDER BY seq",
+        )?;
+        let mut indexes: Vec<SqliteIndex> = stmt
+            .query_map([&self.name, &self.schema], |row| {
+                return Ok(SqliteIndex {
+                    name: row.get(0)?,
+                    unique: row.get(1)?,
+                    origin: row.get(2)?,
+                    partial: row.get(3)?,
+                    columns: Vec::new(),
+                });
+            })?
+            .collect::<Result<_, _>>()?;
+
+        let mut stmt = self
+            .connection
+            .prepare("SELECT name FROM pragma_index_info(?1, ?2) ORDER BY seqno")?;
+        for index in indexes.iter_mut() {
+            index.columns = stmt
+                .query_map([&index.name, &self.schema], |row| {
+                    // NULL for expressions and the rowid
+                    let name: Option<String> = row.get(0)?;
+                    return Ok(name.unwrap_or_else(|| "<expression>".to_string()));
+                })?
+                .collect::<Result<_, _>>()?;
+        }
+        return Ok(indexes);
+    }
+
+    pub fn foreign_keys(&self) -> Result<Vec<SqliteForeignKey>, Error> {
+        let mut stmt = self.connection.prepare(
+            "SELECT id, \"table\", \"from\", \"to\", on_update, on_delete FROM pragma_foreign_key_list(?1, ?2) ORDER BY id, seq",
+        )?;
+        let mut rows = stmt.query([&self.name, &self.schema])?;
+        let mut keys: Vec<SqliteForeignKey> = Vec::new();
+        while let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            let pair = (row.get(2)?, row.get(3)?);
+            match keys.last_mut() {
+                Some(key) if key.id == id => key.columns.push(pair),
+                _ => keys.push(SqliteForeignKey {
+                    id,
+                    table: row.get(1)?,
+                    columns: vec![pair],
+                    on_update: row.get(4)?,
+                    on_delete: row.get(5)?,
+                }),
+            }
+        }
+        return Ok(keys);
+    }
+
+    pub fn triggers(&self) -> Result<Vec<SqliteTrigger>, Error> {
+        let mut stmt = self.connection.prepare(&format!(
+            "SELECT name, sql FROM {} WHERE type = 'trigger' AND tbl_name = ?1 ORDER BY name",
+            self.master_table()
+        ))?;
+        return stmt
+            .query_map([&self.name], |row| {
+                return Ok(SqliteTrigger {
+                    name: row.get(0)?,
+                    sql: row.get(1)?,
+                });
+            })?
+            .collect();
+    }
+
+    /// The statement the table or view was created with, as stored in
+    /// `sqlite_master`.
+    pub fn create_sql(&self) -> Result<Option<String>, Error> {
+        return self
+            .connection
+            .query_row(
+                &format!(
+                    "SELECT sql FROM {} WHERE type IN ('table', 'view') AND name = ?1",
+                    self.master_table()
+                ),
+                [&self.name],
+                |row| row.get(0),
+            )
+            .optional()
+            .map(Option::flatten);
+    }
+
+    pub fn row_count(&self) -> Result<i64, Error> {
+        let sql = format!(
+            "SELECT count(*) FROM {}.{}",
+            quote_identifier(&self.schema),
+            quote_identifier(&self.name)
+        );
+        return self.connection.query_row(&sql, [], |row| row.get(0));
+    }
+
+    pub fn schema(&self) -> Result<TableSchema, Error> {
+        return Ok(TableSchema {
+            name: self.name.clone(),
+            schema: self.schema.clone(),
+            columns: self.columns()?.into_values().collect(),
+            indexes: self.indexes()?,
+            foreign_keys: self.foreign_keys()?,
+            triggers: self.triggers()?,
+            create_sql: self.create_sql()?,
+            row_count: self.row_count()?,
+        });
+    }
+}
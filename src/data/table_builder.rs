@@ -0,0 +1,318 @@
+use num_enum::TryFromPrimitive;
+use rusqlite::{Connection, types::Value};
+use strum::EnumCount;
+
+use super::{
+    identifier::quote_identifier,
+    result_format::{BlobEncoding, sql_literal},
+    sqlite_table::SqliteColumn,
+};
+use crate::error::AppError;
+
+#[repr(usize)]
+#[derive(EnumCount, TryFromPrimitive, Clone, Copy, PartialEq, Eq, Debug)]
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+pub enum ColumnField {
+    NAME = 0,
+    TYPE = 1,
+    NOT_NULL = 2,
+    DEFAULT = 3,
+    PRIMARY_KEY = 4,
+    UNIQUE = 5,
+    CHECK = 6,
+}
+impl ColumnField {
+    pub fn label(&self) -> &'static str {
+        return match self {
+            ColumnField::NAME => "Name",
+            ColumnField::TYPE => "Type",
+            ColumnField::NOT_NULL => "Not null",
+            ColumnField::DEFAULT => "Default",
+            ColumnField::PRIMARY_KEY => "PK",
+            ColumnField::UNIQUE => "Unique",
+            ColumnField::CHECK => "Check",
+        };
+    }
+    pub fn is_toggle(&self) -> bool {
+        return matches!(
+            self,
+            ColumnField::NOT_NULL | ColumnField::PRIMARY_KEY | ColumnField::UNIQUE
+        );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TableBuilderFocus {
+    TableName,
+    Column(usize, ColumnField),
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct ColumnDefinition {
+    pub name: String,
+    pub declared_type: String,
+    pub not_null: bool,
+    pub default_value: String,
+    pub primary_key: bool,
+    pub unique: bool,
+    pub check: String,
+}
+impl ColumnDefinition {
+    pub fn text(&self, field: ColumnField) -> Option<&String> {
+        return match field {
+            ColumnField::NAME => Some(&self.name),
+            ColumnField::TYPE => Some(&self.declared_type),
+            ColumnField::DEFAULT => Some(&self.default_value),
+            ColumnField::CHECK => Some(&self.check),
+            _ => None,
+        };
+    }
+    pub fn text_mut(&mut self, field: ColumnField) -> Option<&mut String> {
+        return match field {
+            ColumnField::NAME => Some(&mut self.name),
+            ColumnField::TYPE => Some(&mut self.declared_type),
+            ColumnField::DEFAULT => Some(&mut self.default_value),
+            ColumnField::CHECK => Some(&mut self.check),
+            _ => None,
+        };
+    }
+    pub fn flag(&self, field: ColumnField) -> Option<bool> {
+        return match field {
+            ColumnField::NOT_NULL => Some(self.not_null),
+            ColumnField::PRIMARY_KEY => Some(self.primary_key),
+            ColumnField::UNIQUE => Some(self.unique),
+            _ => None,
+        };
+    }
+    pub fn toggle(&mut self, field: ColumnField) {
+        match field {
+            ColumnField::NOT_NULL => self.not_null = !self.not_null,
+            ColumnField::PRIMARY_KEY => self.primary_key = !self.primary_key,
+            ColumnField::UNIQUE => self.unique = !self.unique,
+            _ => (),
+        }
+    }
+}
+
+/// The form row for a column read from the database or inferred by an
+/// import.
+impl From<&SqliteColumn> for ColumnDefinition {
+    fn from(column: &SqliteColumn) -> Self {
+        let default_value = match column.default_value {
+            Value::Null => String::new(),
+            ref value => sql_literal(value, BlobEncoding::HEX),
+        };
+        return Self {
+            name: column.name.clone(),
+            declared_type: column.declared_type.clone(),
+            not_null: column.not_null,
+            default_value,
+            primary_key: column.pk > 0,
+            unique: false,
+            check: String::new(),
+        };
+    }
+}
+
+/// Form state behind the "Create" table option.
+pub struct TableBuilder {
+    pub name: String,
+    pub columns: Vec<ColumnDefinition>,
+    pub focus: TableBuilderFocus,
+}
+impl TableBuilder {
+    pub fn new() -> Self {
+        return Self {
+            name: String::new(),
+            columns: vec![ColumnDefinition::default()],
+            focus: TableBuilderFocus::TableName,
+        };
+    }
+
+    /// Builds the `CREATE TABLE` statement for the current form contents.
+    /// A single primary key column gets an inline constraint, several of them
+    /// become a table level `PRIMARY KEY (...)` clause.
+    pub fn to_sql(&self) -> String {
+        let primary_keys: Vec<&ColumnDefinition> =
+            self.columns.iter().filter(|c| c.primary_key).collect();
+        let mut definitions: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| {
+                let mut definition = quote_identifier(&column.name);
+                if !column.declared_type.trim().is_empty() {
+                    definition.push(' ');
+                    definition.push_str(column.declared_type.trim());
+                }
+                if column.primary_key && primary_keys.len() == 1 {
+                    definition.push_str(" PRIMARY KEY");
+                }
+                if column.not_null {
+                    definition.push_str(" NOT NULL");
+                }
+                if column.unique {
+                    definition.push_str(" UNIQUE");
+                }
+                if !column.default_value.trim().is_empty() {
+                    definition.push_str(" DEFAULT ");
+                    definition.push_str(column.default_value.trim());
+                }
+                if !column.check.trim().is_empty() {
+                    definition.push_str(&format!(" CHECK ({})", column.check.trim()));
+                }
+                return definition;
+            })
+            .collect();
+        if primary_keys.len() > 1 {
+            let key_names: Vec<String> = primary_keys
+                .iter()
+                .map(|c| quote_identifier(&c.name))
+                .collect();
+            definitions.push(format!("PRIMARY KEY ({})", key_names.join(", ")));
+        }
+
+        return format!(
+            "CREATE TABLE {} (\n    {}\n);",
+            quote_identifier(&self.name),
+            definitions.join(",\n    ")
+        );
+    }
+
+    /// Runs `to_sql`. The default and check texts are pasted in as they are,
+    /// so it runs as a single statement: text that ends it early is refused
+    /// instead of running whatever follows.
+    pub fn create(&self, connection: &Connection) -> Result<(), AppError> {
+        if self.name.trim().is_empty() {
+            return Err(invalid_form("table name cannot be empty".to_string()));
+        }
+        if let Some(index) = self.columns.iter().position(|c| c.name.trim().is_empty()) {
+            return Err(invalid_form(format!("column {} has no name", index + 1)));
+        }
+        connection.execute(&self.to_sql(), [])?;
+        return Ok(());
+    }
+
+    pub fn focused_text_mut(&mut self) -> Option<&mut String> {
+        return match self.focus {
+            TableBuilderFocus::TableName => Some(&mut self.name),
+            TableBuilderFocus::Column(index, field) => self.columns[index].text_mut(field),
+        };
+    }
+
+    pub fn toggle_focused(&mut self) {
+        if let TableBuilderFocus::Column(index, field) = self.focus {
+            self.columns[index].toggle(field);
+        }
+    }
+
+    pub fn add_column(&mut self) {
+        self.columns.push(ColumnDefinition::default());
+        self.focus = TableBuilderFocus::Column(self.columns.len() - 1, ColumnField::NAME);
+    }
+
+    pub fn remove_focused_column(&mut self) {
+        if let TableBuilderFocus::Column(index, field) = self.focus {
+            if self.columns.len() == 1 {
+                self.columns[0] = ColumnDefinition::default();
+                return;
+            }
+            self.columns.remove(index);
+            self.focus = TableBuilderFocus::Column(index.min(self.columns.len() - 1), field);
+        }
+    }
+
+    pub fn focus_next_field(&mut self) {
+        self.focus = match self.focus {
+            TableBuilderFocus::TableName => TableBuilderFocus::Column(0, ColumnField::NAME),
+            TableBuilderFocus::Column(index, field) => {
+                let next = field as usize + 1;
+                if next < ColumnField::COUNT {
+                    TableBuilderFocus::Column(index, ColumnField::try_from(next).unwrap())
+                } else if index + 1 < self.columns.len() {
+                    TableBuilderFocus::Column(index + 1, ColumnField::NAME)
+                } else {
+                    TableBuilderFocus::TableName
+                }
+            }
+        };
+    }
+
+    pub fn focus_previous_field(&mut self) {
+        self.focus = match self.focus {
+            TableBuilderFocus::TableName => TableBuilderFocus::Column(
+                self.columns.len() - 1,
+                ColumnField::try_from(ColumnField::COUNT - 1).unwrap(),
+            ),
+            TableBuilderFocus::Column(index, field) => {
+                if field as usize > 0 {
+                    TableBuilderFocus::Column(
+                        index,
+                        ColumnField::try_from(field as usize - 1).unwrap(),
+                    )
+                } else if index > 0 {
+                    TableBuilderFocus::Column(
+                        index - 1,
+                        ColumnField::try_from(ColumnField::COUNT - 1).unwrap(),
+                    )
+                } else {
+                    TableBuilderFocus::TableName
+                }
+            }
+        };
+    }
+
+    pub fn focus_up(&mut self) {
+        self.focus = match self.focus {
+            TableBuilderFocus::TableName => TableBuilderFocus::TableName,
+            TableBuilderFocus::Column(0, _) => TableBuilderFocus::TableName,
+            TableBuilderFocus::Column(index, field) => TableBuilderFocus::Column(index - 1, field),
+        };
+    }
+
+    pub fn focus_down(&mut self) {
+        self.focus = match self.focus {
+            TableBuilderFocus::TableName => TableBuilderFocus::Column(0, ColumnField::NAME),
+            TableBuilderFocus::Column(index, field) => {
+                TableBuilderFocus::Column((index + 1).min(self.columns.len() - 1), field)
+            }
+        };
+    }
+}
+
+impl Default for TableBuilder {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+fn invalid_form(message: String) -> AppError {
+    return AppError::UI(message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_and_checks_cannot_add_statements() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection.execute_batch("CREATE TABLE kept (x)").unwrap();
+        let mut builder = TableBuilder::new();
+        builder.name = "t".to_string();
+        builder.columns[0].name = "a".to_string();
+        builder.columns[0].default_value = "1); DROP TABLE kept; --".to_string();
+        assert!(builder.create(&connection).is_err());
+
+        builder.columns[0].default_value = "1".to_string();
+        builder.columns[0].check = "a > 0".to_string();
+        builder.create(&connection).unwrap();
+        let tables: i64 = connection
+            .query_row(
+                "SELECT count(*) FROM sqlite_master WHERE name IN ('kept', 't')",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(tables, 2);
+    }
+}
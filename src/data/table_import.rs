@@ -0,0 +1,233 @@
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use super::{
+    import_format::{Import, ImportFormat, read_file},
+    object_tree::SchemaObjectKind,
+    sqlite_database::{SqliteDatabaseState, SqliteDatabaseStateMode, read_only_error},
+};
+use crate::error::AppError;
+
+/// How long a tick may spend inserting, so keys are still read while a big
+/// file is imported.
+const TICK_BUDGET: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum ImportField {
+    PATH,
+    FORMAT,
+    TABLE,
+}
+impl ImportField {
+    const ALL: [ImportField; 3] = [ImportField::PATH, ImportField::FORMAT, ImportField::TABLE];
+}
+
+/// The file to import and where it goes. The first Enter reads the file and
+/// shows what the import will do, the second one starts it.
+pub struct ImportDialog {
+    pub path: String,
+    /// `None` goes by the file's extension.
+    pub format: Option<ImportFormat>,
+    /// Named after the file when left empty.
+    pub table: String,
+    pub focus: ImportField,
+    pub import: Option<Import>,
+    pub running: bool,
+    /// Finished or stopped, the report is shown.
+    pub done: bool,
+}
+impl ImportDialog {
+    fn new(table: String) -> Self {
+        return Self {
+            path: String::new(),
+            format: None,
+            table,
+            focus: ImportField::PATH,
+            import: None,
+            running: false,
+            done: false,
+        };
+    }
+
+    pub fn effective_format(&self) -> ImportFormat {
+        return self
+            .format
+            .unwrap_or_else(|| ImportFormat::from_path(Path::new(self.path.trim())));
+    }
+
+    pub fn effective_table(&self) -> String {
+        if !self.table.trim().is_empty() {
+            return self.table.trim().to_string();
+        }
+        return Path::new(self.path.trim())
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+    }
+
+    fn move_focus(&mut self, step: isize) {
+        let all = ImportField::ALL;
+        let index = all.iter().position(|f| *f == self.focus).unwrap_or(0) as isize;
+        self.focus = all[(index + step).rem_euclid(all.len() as isize) as usize];
+    }
+
+    /// Auto, then every format.
+    fn cycle_format(&mut self, step: isize) {
+        let mut choices = vec![None];
+        choices.extend(ImportFormat::ALL.map(Some));
+        let index = choices.iter().position(|f| *f == self.format).unwrap_or(0) as isize;
+        self.format = choices[(index + step).rem_euclid(choices.len() as isize) as usize];
+    }
+}
+
+/// Importing CSV, TSV and JSON files from the "Import" table option.
+impl SqliteDatabaseState {
+    /// Opens the dialog, importing into the highlighted table by default.
+    pub fn open_import_dialog(&mut self) {
+        if self.database.readonly {
+            self.error = Some(read_only_error());
+            return;
+        }
+        let table = self
+            .object_tree
+            .selected_object()
+            .filter(|object| object.kind == SchemaObjectKind::TABLE && object.schema == "main")
+            .map(|object| object.name.clone())
+            .unwrap_or_default();
+        self.import_dialog = Some(ImportDialog::new(table));
+        self.error = None;
+        self.mode = SqliteDatabaseStateMode::IMPORT;
+    }
+
+    fn close_import_dialog(&mut self) {
+        let imported = self
+            .import_dialog
+            .take()
+            .and_then(|dialog| dialog.import)
+            .filter(|import| import.next_row > 0 || import.is_finished())
+            .map(|import| import.table);
+        self.error = None;
+        match imported {
+            Some(table) => {
+                self.reload_schema();
+                self.object_tree.select_object("main", &table);
+                self.selected_table_option = None;
+                self.mode = SqliteDatabaseStateMode::TABLE_SELECTION;
+            }
+            None => self.mode = SqliteDatabaseStateMode::TABLE_OPTION_SELECTION,
+        }
+    }
+
+    /// Reads the file and works out the columns, nothing is written yet.
+    fn prepare_import(&mut self) {
+        let Some(dialog) = &mut self.import_dialog else {
+            return;
+        };
+        let path = dialog.path.trim().to_string();
+        if path.is_empty() {
+            self.error = Some(AppError::UI("enter the path of the file".to_string()));
+            return;
+        }
+        let prepared = read_file(Path::new(&path), dialog.effective_format()).and_then(|data| {
+            return Import::prepare(&self.database.connection, &dialog.effective_table(), data);
+        });
+        match prepared {
+            Ok(import) => {
+                dialog.import = Some(import);
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    /// Inserts batches for a while when an import is running. Called on
+    /// every tick, returns whether there was anything to do.
+    pub fn step_import(&mut self) -> bool {
+        let Some(dialog) = &mut self.import_dialog else {
+            return false;
+        };
+        let Some(import) = dialog.import.as_mut().filter(|_| dialog.running) else {
+            return false;
+        };
+        let started = Instant::now();
+        while !import.is_finished() && started.elapsed() < TICK_BUDGET {
+            if let Err(e) = import.run_batch(&self.database.connection) {
+                self.error = Some(e);
+                break;
+            }
+        }
+        if import.is_finished() || self.error.is_some() {
+            dialog.running = false;
+            dialog.done = true;
+            self.reload_schema();
+        }
+        return true;
+    }
+
+    pub(crate) fn read_import_keys(&mut self, event: &KeyEvent) {
+        let Some(dialog) = &mut self.import_dialog else {
+            self.mode = SqliteDatabaseStateMode::TABLE_OPTION_SELECTION;
+            return;
+        };
+        if dialog.running {
+            // stops after the current batch, the batches before it stay
+            if event.code == KeyCode::Esc {
+                dialog.running = false;
+                dialog.done = true;
+                self.reload_schema();
+            }
+            return;
+        }
+        if dialog.done {
+            if matches!(event.code, KeyCode::Esc | KeyCode::Enter) {
+                self.close_import_dialog();
+            }
+            return;
+        }
+        match (event.code, dialog.focus) {
+            (KeyCode::Esc, _) => self.close_import_dialog(),
+            (KeyCode::Enter, _) if dialog.import.is_some() => dialog.running = true,
+            (KeyCode::Enter, _) => self.prepare_import(),
+            (KeyCode::Down | KeyCode::Tab, _) => dialog.move_focus(1),
+            (KeyCode::Up | KeyCode::BackTab, _) => dialog.move_focus(-1),
+            (KeyCode::Left, ImportField::FORMAT) => {
+                dialog.import = None;
+                dialog.cycle_format(-1);
+            }
+            (KeyCode::Right, ImportField::FORMAT) => {
+                dialog.import = None;
+                dialog.cycle_format(1);
+            }
+            (KeyCode::Backspace, ImportField::PATH) => {
+                dialog.import = None;
+                dialog.path.pop();
+            }
+            (KeyCode::Backspace, ImportField::TABLE) => {
+                dialog.import = None;
+                dialog.table.pop();
+            }
+            (KeyCode::Char(c), ImportField::PATH)
+                if !event
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                dialog.import = None;
+                dialog.path.push(c);
+            }
+            (KeyCode::Char(c), ImportField::TABLE)
+                if !event
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                dialog.import = None;
+                dialog.table.push(c);
+            }
+            _ => (),
+        }
+    }
+}
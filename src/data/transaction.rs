@@ -0,0 +1,194 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use sqlparser::{
+    dialect::SQLiteDialect,
+    keywords::Keyword,
+    tokenizer::{Token, Tokenizer},
+};
+
+use super::sqlite_database::SqliteDatabaseState;
+use crate::error::AppError;
+
+/// Whether one of `statements` starts or ends a transaction or savepoint.
+/// These run on the UI's connection, see `SqliteDatabaseState::execute`.
+pub fn controls_transaction(statements: &[String]) -> bool {
+    return statements.iter().any(|sql| {
+        let tokens = Tokenizer::new(&SQLiteDialect {}, sql)
+            .tokenize()
+            .unwrap_or_default();
+        let first_word = tokens.iter().find(|t| !matches!(t, Token::Whitespace(_)));
+        return matches!(
+            first_word,
+            Some(Token::Word(w)) if matches!(
+                w.keyword,
+                Keyword::BEGIN
+                    | Keyword::COMMIT
+                    | Keyword::END
+                    | Keyword::ROLLBACK
+                    | Keyword::SAVEPOINT
+                    | Keyword::RELEASE
+            )
+        );
+    });
+}
+
+/// Explicit transactions. They live on the UI's connection, the one results
+/// are paged and edits are applied on, so everything done while one is open
+/// sees its changes and a rollback undoes edits as well.
+impl SqliteDatabaseState {
+    /// A transaction is open and nothing in it is committed yet.
+    pub fn in_transaction(&self) -> bool {
+        return !self.database.connection.is_autocommit();
+    }
+
+    /// Begins a transaction, or commits the open one.
+    pub fn toggle_transaction(&mut self) {
+        if self.in_transaction() {
+            self.run_transaction_statement("COMMIT".to_string());
+        } else {
+            self.run_transaction_statement("BEGIN".to_string());
+        }
+    }
+
+    /// Rolls back to the newest savepoint, or the whole transaction when
+    /// there is none.
+    pub fn rollback(&mut self) {
+        if !self.in_transaction() {
+            return;
+        }
+        let Some(name) = self.savepoints.last() else {
+            self.run_transaction_statement("ROLLBACK".to_string());
+            return;
+        };
+        if self.run_transaction_statement(format!("ROLLBACK TO {0}; RELEASE {0}", name)) {
+            self.savepoints.pop();
+        }
+    }
+
+    /// Marks the current state of the open transaction so it can be rolled
+    /// back to.
+    pub fn create_savepoint(&mut self) {
+        if !self.in_transaction() {
+            // outside of a transaction releasing it would commit
+            self.error = Some(AppError::UI(
+                "savepoints need an open transaction, begin one with F9".to_string(),
+            ));
+            return;
+        }
+        let name = format!("sp{}", self.savepoints.len() + 1);
+        if self.run_transaction_statement(format!("SAVEPOINT {}", name)) {
+            self.savepoints.push(name);
+        }
+    }
+
+    fn run_transaction_statement(&mut self, sql: String) -> bool {
+        if self.running_query.is_some() {
+            return false;
+        }
+        if let Err(e) = self.database.connection.execute_batch(&sql) {
+            self.error = Some(e.into());
+            return false;
+        }
+        self.error = None;
+        self.end_transaction_if_closed();
+        // a rollback may have undone what the result shows
+        self.reload_schema();
+        if self
+            .queried_table_state
+            .as_ref()
+            .is_some_and(|result| result.is_readonly())
+        {
+            self.refresh_result();
+        }
+        return true;
+    }
+
+    /// Forgets the savepoints once the transaction is gone, e.g. after a
+    /// COMMIT typed in the query tool.
+    pub(crate) fn end_transaction_if_closed(&mut self) {
+        if !self.in_transaction() {
+            self.savepoints.clear();
+        }
+    }
+
+    /// F9 begins or commits, F10 rolls back and F7 sets a savepoint. Returns
+    /// false for other keys.
+    pub(crate) fn read_transaction_keys(&mut self, event: &KeyEvent) -> bool {
+        match event.code {
+            KeyCode::F(9) => self.toggle_transaction(),
+            KeyCode::F(10) => self.rollback(),
+            KeyCode::F(7) => self.create_savepoint(),
+            _ => return false,
+        }
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::super::sqlite_database::SqliteDatabase;
+    use super::*;
+
+    fn statements(sql: &[&str]) -> Vec<String> {
+        return sql.iter().map(|s| s.to_string()).collect();
+    }
+
+    #[test]
+    fn finds_statements_that_control_transactions() {
+        for sql in [
+            "BEGIN",
+            "begin immediate",
+            "  COMMIT",
+            "END TRANSACTION",
+            "ROLLBACK TO sp1",
+            "SAVEPOINT a",
+            "RELEASE a",
+        ] {
+            assert!(
+                controls_transaction(&statements(&["SELECT 1", sql])),
+                "{}",
+                sql
+            );
+        }
+        assert!(!controls_transaction(&statements(&[
+            "SELECT 'BEGIN'",
+            "CREATE TRIGGER x AFTER INSERT ON t BEGIN SELECT 1; END",
+            "-- COMMIT",
+        ])));
+    }
+
+    #[test]
+    fn rollback_goes_back_to_the_newest_savepoint() {
+        let database = SqliteDatabase::open(PathBuf::from(":memory:"), false, true).unwrap();
+        database
+            .connection
+            .execute_batch("CREATE TABLE t (a)")
+            .unwrap();
+        let count = || -> i64 {
+            return database
+                .connection
+                .query_row("SELECT count(*) FROM t", [], |row| row.get(0))
+                .unwrap();
+        };
+        let mut state = SqliteDatabaseState::new(&database);
+        state.create_savepoint();
+        assert!(state.error.is_some());
+
+        state.toggle_transaction();
+        assert!(state.in_transaction());
+        state.current_query = "INSERT INTO t VALUES (1)".to_string();
+        state.execute();
+        state.create_savepoint();
+        state.execute();
+        assert_eq!(state.savepoints, vec!["sp1"]);
+        assert_eq!(count(), 2);
+
+        state.rollback();
+        assert_eq!(count(), 1);
+        assert!(state.savepoints.is_empty());
+        state.rollback();
+        assert_eq!(count(), 0);
+        assert!(!state.in_transaction());
+    }
+}
@@ -0,0 +1,50 @@
+use std::{fmt, io};
+
+use rusqlite::ErrorCode;
+
+/// Everything that can go wrong in the viewer. Errors are shown in the UI's
+/// error area, only startup failures end the program.
+#[derive(Debug)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum AppError {
+    SQLITE(rusqlite::Error),
+    IO(io::Error),
+    /// The schema doesn't allow what was asked, e.g. editing a view.
+    SCHEMA(String),
+    /// Input the UI refuses, e.g. a required field left empty.
+    UI(String),
+    /// The user cancelled the running query.
+    INTERRUPTED,
+}
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            AppError::SQLITE(e) => write!(f, "{}", e),
+            AppError::IO(e) => write!(f, "{}", e),
+            AppError::SCHEMA(message) | AppError::UI(message) => write!(f, "{}", message),
+            AppError::INTERRUPTED => write!(f, "query interrupted"),
+        };
+    }
+}
+impl std::error::Error for AppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        return match self {
+            AppError::SQLITE(e) => Some(e),
+            AppError::IO(e) => Some(e),
+            AppError::SCHEMA(_) | AppError::UI(_) | AppError::INTERRUPTED => None,
+        };
+    }
+}
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        if e.sqlite_error_code() == Some(ErrorCode::OperationInterrupted) {
+            return AppError::INTERRUPTED;
+        }
+        return AppError::SQLITE(e);
+    }
+}
+impl From<io::Error> for AppError {
+    fn from(e: io::Error) -> Self {
+        return AppError::IO(e);
+    }
+}
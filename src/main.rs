@@ -1,25 +1,180 @@
-use std::{io, process::CommandEnvs};
+#![allow(clippy::needless_return)]
+
+use std::{
+    error::Error,
+    io::{self, BufWriter, Read, Write},
+};
 
 use clap::Parser;
-use cli::CommandLine;
-use data::sqlite_database::SqliteDatabase;
-use rusqlite::Connection;
+use cli::{Command, CommandLine, ImportArguments};
+use crash::TerminalGuard;
+use data::{
+    import_format::{Import, ImportFormat, read_file},
+    result_format::{BlobEncoding, ExportOptions, ResultFormat, source_table, write_result},
+    sqlite_database::{SqliteDatabase, SqliteDatabaseState, read_only_error},
+};
+use error::AppError;
 use tui::app::App;
 
 mod cli;
+mod crash;
 mod data;
+mod error;
 mod tui;
-fn main() -> Result<(), rusqlite::Error> {
+fn main() -> Result<(), Box<dyn Error>> {
     let command_line = CommandLine::parse();
-    let file_path = command_line.file_path;
-    let conn = Connection::open(&file_path)?;
+    let db = match SqliteDatabase::open(
+        command_line.file_path,
+        command_line.readonly,
+        command_line.create,
+    ) {
+        Ok(db) => db,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Some(Command::IMPORT(arguments)) = command_line.command {
+        if let Err(e) = run_import(&db, arguments) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if let Some(query) = command_line.query {
+        if let Err(e) = run_query(
+            &db,
+            query,
+            command_line.format,
+            command_line.table,
+            command_line.blobs,
+        ) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let (terminal, guard) = TerminalGuard::init();
+    crash::install_panic_hook(db.database_path.clone());
+    let mut app = App::new(terminal, 3, db);
+    let result = app.run();
+    drop(guard);
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+    println!("Application exit requested");
+    return Ok(());
+}
 
-    let db = SqliteDatabase::new(conn, file_path);
-    let terminal = ratatui::init();
-    let mut app = App::new(terminal, 120, 3, db);
+/// Non-interactive mode: runs `query` and prints the result to stdout in
+/// `format`, for the sql format inserting into `table`.
+fn run_query(
+    db: &SqliteDatabase,
+    query: String,
+    format: ResultFormat,
+    table: Option<String>,
+    blobs: BlobEncoding,
+) -> Result<(), AppError> {
+    let query = if query == "-" {
+        let mut query = String::new();
+        io::stdin().read_to_string(&mut query)?;
+        query
+    } else {
+        query
+    };
 
-    while app.draw() {}
-    ratatui::restore();
-    println!("Application exit requested");
+    let mut state = SqliteDatabaseState::new(db);
+    state.set_query(query);
+    state.execute();
+    if state.parameter_dialog.is_some() {
+        return Err(AppError::UI(
+            "the query has parameters, --query can't ask for their values".to_string(),
+        ));
+    }
+    if let Some(report) = &state.script_report {
+        for (index, statement) in report.statements.iter().enumerate() {
+            match &statement.result {
+                Ok(summary) => eprintln!("{}. ok: {}", index + 1, summary),
+                Err(e) => eprintln!("{}. error: {}", index + 1, e),
+            }
+        }
+        if report.skipped > 0 {
+            eprintln!("{} statements skipped", report.skipped);
+        }
+        if report.rolled_back {
+            eprintln!("rolled back");
+        }
+    }
+    if let Some(e) = state.error {
+        return Err(e);
+    }
+    let Some(mut result) = state.queried_table_state else {
+        return Ok(());
+    };
+    if let Some(affected) = result.rows_affected {
+        eprintln!("{} rows affected", affected);
+        return Ok(());
+    }
+    let mut out = BufWriter::new(io::stdout().lock());
+    let options = ExportOptions {
+        table: table
+            .or_else(|| source_table(&db.connection, &result.sql))
+            .unwrap_or(ExportOptions::default().table),
+        blobs,
+    };
+    write_result(&mut result, &db.connection, format, &options, &mut out)?;
+    out.flush()?;
     return Ok(());
 }
+
+/// Imports a file without the TUI. Progress and the rows that failed go to
+/// stderr, failed rows make it end with an error.
+fn run_import(db: &SqliteDatabase, arguments: ImportArguments) -> Result<(), AppError> {
+    if db.readonly {
+        return Err(read_only_error());
+    }
+    let format = arguments
+        .format
+        .unwrap_or_else(|| ImportFormat::from_path(&arguments.file));
+    let table = arguments.table.unwrap_or_else(|| {
+        return arguments
+            .file
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+    });
+    let data = read_file(&arguments.file, format)?;
+    let mut import = Import::prepare(&db.connection, &table, data)?;
+    for line in import.plan() {
+        eprintln!("{}", line);
+    }
+    while !import.is_finished() {
+        import.run_batch(&db.connection)?;
+        eprint!("\r{}", progress_bar(&import));
+    }
+    eprintln!();
+    for error in &import.errors {
+        eprintln!("row {}: {}", error.row, error.message);
+    }
+    eprintln!("{}", import.summary());
+    if !import.errors.is_empty() {
+        return Err(AppError::UI(format!(
+            "{} rows could not be imported",
+            import.errors.len()
+        )));
+    }
+    return Ok(());
+}
+
+fn progress_bar(import: &Import) -> String {
+    const WIDTH: usize = 30;
+    let done = (import.progress() * WIDTH as f64).round() as usize;
+    return format!(
+        "[{}{}] {}/{} rows",
+        "#".repeat(done),
+        "-".repeat(WIDTH - done),
+        import.next_row,
+        import.data.rows.len()
+    );
+}
@@ -1,134 +1,232 @@
 use std::{
     cell::RefCell,
-    collections::HashMap,
-    fmt::Pointer,
-    io::Write,
     rc::Rc,
-    thread,
-    time::{Duration, Instant, SystemTime},
+    time::{Duration, Instant},
 };
 
 use humantime::format_duration;
 use ratatui::{
-    DefaultTerminal, Terminal,
-    crossterm::{
-        ExecutableCommand,
-        event::{Event, KeyCode, KeyEvent, KeyModifiers, poll, read},
-    },
+    DefaultTerminal,
+    crossterm::event::{KeyCode, KeyModifiers},
     layout::{Constraint, Layout},
-    style::{Style, Stylize},
-    text::Line,
-    widgets::{Block, Cell, Paragraph, Row, Table, TableState, Widget},
+    style::Stylize,
+    text::{Line, Span},
+};
+
+use crate::{
+    data::{
+        query_history::QueryHistory,
+        query_parameters::ParameterMemory,
+        sqlite_database::{SqliteDatabase, SqliteDatabaseState},
+    },
+    error::AppError,
 };
-use time_humanize::HumanTime;
 
-use crate::data::sqlite_database::{SqliteDatabase, SqliteDatabaseState};
+use super::event::{AppEvent, EventLoop};
 
 pub struct App {
     terminal: DefaultTerminal,
-
-    // TODO REPLACE WITH INPUT HANDLER THREADING
-    handler: bool,
-    fps: u16,
+    events: EventLoop,
     state: Rc<RefCell<AppState>>,
+    /// Something changed since the last frame.
+    dirty: bool,
+    /// Clocks shown in the footer of the last frame, see `AppState::clock`.
+    drawn_clock: (u64, Option<u64>),
+    /// Ctrl+Q was pressed while a transaction is open, a second one quits.
+    quit_requested: bool,
 }
 impl App {
-    pub fn new(
-        terminal: DefaultTerminal,
-        fps: u16,
-        sync_rate: u16,
-        database: SqliteDatabase,
-    ) -> Self {
+    pub fn new(terminal: DefaultTerminal, sync_rate: u16, database: SqliteDatabase) -> Self {
+        let events = EventLoop::start(sync_rate);
+        let mut state = AppState::new(database);
+        let sender = events.sender();
+        state.database_state.start_worker(move || {
+            let _ = sender.send(AppEvent::QUERY_FINISHED);
+        });
         return Self {
             terminal,
-            handler: false,
-            fps,
-            state: Rc::new(RefCell::new(AppState::new(database, sync_rate))),
+            events,
+            state: Rc::new(RefCell::new(state)),
+            dirty: true,
+            drawn_clock: (0, None),
+            quit_requested: false,
         };
     }
-    pub fn draw(&mut self) -> bool {
-        let mut render_next = true;
-        let start = Instant::now();
-        let frame_time = Duration::from_secs_f64(1.0 / self.fps as f64);
-        self.terminal
-            .draw(|f| {
-                let event = poll(Duration::from_secs(0));
-                if let Ok(true) = event {
-                    let event = read();
-                    if let Ok(Event::Key(key_event)) = &event {
-                        if let KeyCode::Char('q') = key_event.code
-                            && key_event.modifiers.contains(KeyModifiers::CONTROL)
-                        {
-                            render_next = false;
-                        }
-                    }
-                    if let Ok(Event::Key(event)) = &event {
-                        let database_state = &mut self.state.borrow_mut().database_state;
-                        database_state.read_keys(event);
-                    }
-                }
-                let area = f.area();
-                let footer_layout =
-                    Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).split(area);
-                f.render_widget(
-                    self.state.borrow().database_state.widget(),
-                    footer_layout[0],
-                );
-                let database_path = &self.state.borrow().database.database_path;
-                let last_sync =
-                    Duration::from_secs((Instant::now() - self.state.borrow().last_sync).as_secs());
-                let footer = Line::raw(format!(
-                    "Last sync: {} ago | Database name: {} | Database path: {:#?}",
-                    format_duration(last_sync),
-                    database_path
-                        .file_name()
-                        .unwrap()
-                        .to_string_lossy()
-                        .split_once('.')
-                        .unwrap()
-                        .0,
-                    database_path
-                ))
-                .centered();
-                f.render_widget(footer, footer_layout[1]);
-            })
-            .unwrap();
 
-        let (last_sync, sync_rate, database) = {
-            let state = self.state.borrow();
-            (state.last_sync, state.sync_rate, state.database.clone())
-        };
-        let elapsed = start.elapsed();
-        if Instant::now() - last_sync >= Duration::from_secs(sync_rate as u64) {
-            let mut state = self.state.borrow_mut();
-            state.database_state.sync(&database);
-            state.last_sync = Instant::now();
+    /// Draws, then waits for events and draws again after each batch of
+    /// them, until the user asks to quit with Ctrl+Q or the terminal can't be
+    /// read anymore.
+    pub fn run(&mut self) -> Result<(), AppError> {
+        self.render()?;
+        while let Some(event) = self.events.next() {
+            let mut event = Some(event);
+            // handle everything already queued, e.g. pasted text, before drawing
+            while let Some(next) = event {
+                if let AppEvent::INPUT_ERROR(e) = next {
+                    return Err(AppError::IO(e));
+                }
+                if !self.handle_event(next) {
+                    return Ok(());
+                }
+                event = self.events.try_next();
+            }
+            if self.dirty {
+                self.render()?;
+            }
         }
-        if elapsed < frame_time {
-            thread::sleep(frame_time - elapsed);
+        return Ok(());
+    }
+
+    /// Returns false when the app should quit. Quitting with an open
+    /// transaction or pending edits needs Ctrl+Q twice, closing the connection
+    /// rolls the transaction back and the edits are lost.
+    fn handle_event(&mut self, event: AppEvent) -> bool {
+        let mut state = self.state.borrow_mut();
+        match event {
+            AppEvent::KEY(key_event)
+                if key_event.code == KeyCode::Char('q')
+                    && key_event.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                if self.quit_requested || quit_warning(&state.database_state).is_none() {
+                    return false;
+                }
+                self.quit_requested = true;
+            }
+            AppEvent::KEY(key_event) => {
+                self.quit_requested = false;
+                state.database_state.read_keys(&key_event);
+            }
+            AppEvent::SYNC => {
+                state.database_state.sync();
+                state.last_sync = Instant::now();
+            }
+            AppEvent::TICK => {
+                self.dirty |= state.database_state.step_import()
+                    || state.database_state.running_query.is_some()
+                    || state.clock() != self.drawn_clock;
+                return true;
+            }
+            AppEvent::QUERY_FINISHED => state.database_state.poll_query(),
+            AppEvent::RESIZE => (),
+            // `run` stops with the error before handling it
+            AppEvent::INPUT_ERROR(_) => return false,
         }
+        self.dirty = true;
+        return true;
+    }
 
-        return render_next;
+    fn render(&mut self) -> Result<(), AppError> {
+        let state = self.state.borrow();
+        self.dirty = false;
+        self.drawn_clock = state.clock();
+        self.terminal.draw(|f| {
+            let area = f.area();
+            let footer_layout =
+                Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).split(area);
+            f.render_widget(state.database_state.widget(), footer_layout[0]);
+            let database_path = &state.database.database_path;
+            let (sync_seconds, change_seconds) = self.drawn_clock;
+            let mut footer = Line::default();
+            let database_state = &state.database_state;
+            if let Some(warning) = quit_warning(database_state).filter(|_| self.quit_requested) {
+                footer.push_span(Span::raw(format!("{} | ", warning)).red().bold());
+            } else if database_state.in_transaction() {
+                let rollback = match database_state.savepoints.last() {
+                    Some(savepoint) => format!("F10: rollback to {}", savepoint),
+                    None => "F10: rollback".to_string(),
+                };
+                footer.push_span(
+                    Span::raw(format!(
+                        "TRANSACTION | F9: commit | {} | F7: savepoint | ",
+                        rollback
+                    ))
+                    .magenta()
+                    .bold(),
+                );
+            } else {
+                footer.push_span(Span::raw("F9: begin transaction | ").dark_gray());
+            }
+            if let Some(seconds) = change_seconds {
+                footer.push_span(
+                    Span::raw(format!(
+                        "Database changed externally {} ago, F8: refresh result | ",
+                        format_duration(Duration::from_secs(seconds))
+                    ))
+                    .yellow(),
+                );
+            }
+            footer.push_span(Span::raw(format!(
+                "{}Last sync: {} ago | Database name: {} | Database path: {:#?}",
+                if state.database.readonly {
+                    "READ ONLY | "
+                } else {
+                    ""
+                },
+                format_duration(Duration::from_secs(sync_seconds)),
+                database_path
+                    .file_stem()
+                    .unwrap_or(database_path.as_os_str())
+                    .to_string_lossy(),
+                database_path
+            )));
+            f.render_widget(footer.centered(), footer_layout[1]);
+        })?;
+        return Ok(());
     }
 }
 
 pub struct AppState {
     pub database: SqliteDatabase,
-    pub sync_rate: u16,
     pub last_sync: Instant,
     pub database_state: SqliteDatabaseState,
 }
 
 impl AppState {
-    pub fn new(database: SqliteDatabase, sync_rate: u16) -> Self {
-        let database_state = SqliteDatabaseState::new(&database);
+    pub fn new(database: SqliteDatabase) -> Self {
+        let mut database_state = SqliteDatabaseState::new(&database);
+        database_state.history = QueryHistory::for_database(&database.database_path);
+        database_state.parameter_memory = ParameterMemory::for_database(&database.database_path);
         let state = Self {
             database,
-            sync_rate,
             last_sync: Instant::now(),
             database_state,
         };
 
         return state;
     }
+
+    /// Whole seconds since the last sync and since an external change was
+    /// noticed. The footer only needs a redraw when these change.
+    fn clock(&self) -> (u64, Option<u64>) {
+        return (
+            self.last_sync.elapsed().as_secs(),
+            self.database_state
+                .external_change
+                .map(|at| at.elapsed().as_secs()),
+        );
+    }
+}
+
+/// What would be lost by quitting now, `None` when nothing would.
+fn quit_warning(state: &SqliteDatabaseState) -> Option<String> {
+    let edits = state.pending_edits.len();
+    let edits = format!(
+        "{} pending edit{}",
+        edits,
+        if edits == 1 { "" } else { "s" }
+    );
+    return match (state.in_transaction(), state.pending_edits.is_empty()) {
+        (false, true) => None,
+        (true, true) => {
+            Some("Uncommitted transaction! Ctrl+Q: quit and roll back, F9: commit".to_string())
+        }
+        (false, false) => Some(format!(
+            "{}! Ctrl+Q: quit and discard them, Ctrl+S in the result: commit",
+            edits
+        )),
+        (true, false) => Some(format!(
+            "{} and an uncommitted transaction! Ctrl+Q: quit, discard and roll back",
+            edits
+        )),
+    };
 }
@@ -0,0 +1,94 @@
+use std::{
+    io,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+use ratatui::crossterm::event::{self, Event, KeyEvent};
+
+/// How often `TICK` is sent. Ticks only cause a redraw when something on
+/// screen depends on time, like the spinner of a running query.
+pub const TICK_RATE: Duration = Duration::from_millis(100);
+
+/// Everything the app reacts to. The screen is only redrawn after one of
+/// these arrives.
+#[derive(Debug)]
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+pub enum AppEvent {
+    KEY(KeyEvent),
+    RESIZE,
+    /// Keeps clocks like the time since the last sync current.
+    TICK,
+    /// Time to read the schema again.
+    SYNC,
+    /// A query running in the background finished.
+    QUERY_FINISHED,
+    /// The terminal can't be read anymore, no keys will arrive.
+    INPUT_ERROR(io::Error),
+}
+
+/// Channel fed by the input thread, the timer thread and background work.
+pub struct EventLoop {
+    sender: Sender<AppEvent>,
+    receiver: Receiver<AppEvent>,
+}
+impl EventLoop {
+    /// Starts the input and timer threads. `SYNC` is sent every `sync_rate`
+    /// seconds.
+    pub fn start(sync_rate: u16) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        let input = sender.clone();
+        thread::spawn(move || {
+            loop {
+                let event = match event::read() {
+                    Ok(Event::Key(key)) => AppEvent::KEY(key),
+                    Ok(Event::Resize(_, _)) => AppEvent::RESIZE,
+                    Ok(_) => continue,
+                    Err(e) => {
+                        let _ = input.send(AppEvent::INPUT_ERROR(e));
+                        return;
+                    }
+                };
+                if input.send(event).is_err() {
+                    return;
+                }
+            }
+        });
+
+        let timer = sender.clone();
+        let ticks_per_sync = (Duration::from_secs(sync_rate.max(1) as u64).as_millis()
+            / TICK_RATE.as_millis()) as u32;
+        thread::spawn(move || {
+            let mut ticks: u32 = 0;
+            loop {
+                thread::sleep(TICK_RATE);
+                ticks += 1;
+                if timer.send(AppEvent::TICK).is_err() {
+                    return;
+                }
+                if ticks.is_multiple_of(ticks_per_sync) && timer.send(AppEvent::SYNC).is_err() {
+                    return;
+                }
+            }
+        });
+
+        return Self { sender, receiver };
+    }
+
+    /// For work running on other threads to report back.
+    pub fn sender(&self) -> Sender<AppEvent> {
+        return self.sender.clone();
+    }
+
+    /// Waits for the next event.
+    pub fn next(&self) -> Option<AppEvent> {
+        return self.receiver.recv().ok();
+    }
+
+    /// The next event if one is already queued.
+    pub fn try_next(&self) -> Option<AppEvent> {
+        return self.receiver.try_recv().ok();
+    }
+}
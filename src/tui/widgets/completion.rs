@@ -0,0 +1,65 @@
+use ratatui::{
+    prelude::{Buffer, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Clear, List, ListState, StatefulWidget, Widget},
+};
+
+use crate::data::completion::CompletionPopup;
+
+/// Suggestion list drawn below the cursor. `area` only gives the anchor
+/// position, the size comes from the suggestions and is kept inside `buf`.
+pub struct CompletionPopupWidget<'a> {
+    pub popup: &'a CompletionPopup,
+}
+
+impl<'a> Widget for CompletionPopupWidget<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let label_width = 8;
+        let text_width = self
+            .popup
+            .items
+            .iter()
+            .map(|c| c.text.chars().count())
+            .max()
+            .unwrap_or(0) as u16;
+        let width = (text_width + label_width + 3).min(buf.area.width);
+        let height = (self.popup.items.len() as u16 + 2).min(buf.area.height);
+        // keep the popup on screen, flipping it above the cursor when there is no room below
+        let x = area.x.min(buf.area.right().saturating_sub(width));
+        let y = if area.y + height > buf.area.bottom() {
+            area.y.saturating_sub(height + 1)
+        } else {
+            area.y
+        };
+        let area = Rect::new(x, y, width, height);
+
+        let items = self.popup.items.iter().map(|completion| {
+            return Line::from(vec![
+                Span::raw(format!(
+                    "{:<width$} ",
+                    completion.text,
+                    width = text_width as usize
+                )),
+                Span::raw(format!(
+                    "{:>width$}",
+                    completion.kind.label(),
+                    width = label_width as usize
+                ))
+                .dark_gray(),
+            ]);
+        });
+        Clear.render(area, buf);
+        StatefulWidget::render(
+            List::new(items)
+                .highlight_style(Style::new().reversed())
+                .block(Block::bordered().blue()),
+            area,
+            buf,
+            &mut ListState::default().with_selected(Some(self.popup.selected)),
+        );
+    }
+}
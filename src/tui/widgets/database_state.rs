@@ -1,8 +1,9 @@
 use ratatui::{
     layout::{Constraint, Layout},
+    prelude::{Buffer, Rect},
     style::{Style, Stylize},
     text::{Line, Span, Text},
-    widgets::{Block, List, Padding, Paragraph, Row, Table, Widget},
+    widgets::{Block, List, ListState, Padding, Paragraph, StatefulWidget, Widget},
 };
 use sqlparser::{
     dialect::SQLiteDialect,
@@ -10,10 +11,82 @@ use sqlparser::{
     tokenizer::{Token, Tokenizer},
 };
 
-use crate::data::sqlite_database::{SqliteDatabaseState, SqliteDatabaseStateMode, TableOption};
+use crate::{
+    data::{
+        object_tree::ObjectTreeNode,
+        script::{ScriptReport, split_statements},
+        sqlite_database::{SqliteDatabaseState, SqliteDatabaseStateMode, TableOption},
+        sqlite_query::SqliteQueryResult,
+    },
+    error::AppError,
+};
+
+use super::{
+    completion::CompletionPopupWidget, export_dialog::ExportDialogWidget, history::HistoryWidget,
+    import_dialog::ImportDialogWidget, parameter_dialog::ParameterDialogWidget,
+    query_plan::QueryPlanWidget, result_grid::ResultGridWidget, row_form::RowFormWidget,
+    schema_view::SchemaViewWidget, table_builder::TableBuilderWidget,
+};
+
+/// Timing and statement counters of the last execution. Counters that point
+/// at missing indexes are highlighted.
+fn stats_line(result: &SqliteQueryResult) -> Line<'static> {
+    let stats = &result.stats;
+    let counter = |label: &str, value: i32| {
+        let span = Span::raw(format!(" | {} {}", label, value));
+        return if value > 0 { span.yellow() } else { span };
+    };
+    let mut spans = vec![
+        Span::raw(format!("{:.2?}", stats.duration)).bold(),
+        Span::raw(format!(" | {}", result.summary())),
+        counter("full scan steps", stats.counters.fullscan_steps),
+        counter("sorts", stats.counters.sorts),
+        counter("autoindexes", stats.counters.autoindexes),
+        Span::raw(format!(" | VM steps {}", stats.counters.vm_steps)),
+    ];
+    if let Some(rowid) = stats.last_insert_rowid {
+        spans.push(Span::raw(format!(" | last insert rowid {}", rowid)));
+    }
+    return Line::from(spans).dark_gray();
+}
+
+/// A line per statement of a script, failed ones with their error.
+fn script_lines(report: &ScriptReport) -> Vec<Line<'static>> {
+    let ran = report.statements.len();
+    let mut summary = format!("Script: {} statements ran, {} failed", ran, report.failed());
+    if report.skipped > 0 {
+        summary.push_str(&format!(", {} skipped", report.skipped));
+    }
+    let mut lines = vec![Line::raw(summary).bold()];
+    for (index, statement) in report.statements.iter().enumerate() {
+        let first_line = statement.sql.lines().next().unwrap_or_default();
+        let sql = if first_line.chars().count() > 60 || first_line.len() < statement.sql.len() {
+            format!("{}…", first_line.chars().take(60).collect::<String>())
+        } else {
+            first_line.to_string()
+        };
+        let (mark, outcome) = match &statement.result {
+            Ok(summary) => (Span::raw("✓ ").green(), Span::raw(summary.clone())),
+            Err(e) => (Span::raw("✗ ").red(), Span::raw(e.clone()).red()),
+        };
+        lines.push(Line::from(vec![
+            mark,
+            Span::raw(format!("{:>3}. {}  ", index + 1, sql)),
+            outcome,
+            Span::raw(format!(" ({:.2?})", statement.duration)).dark_gray(),
+        ]));
+    }
+    if report.rolled_back {
+        lines.push(Line::raw("Rolled back, the script changed nothing").yellow());
+    }
+    return lines;
+}
+
+/// Frames of the running query spinner, one per 100ms.
+const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
 
 impl SqliteDatabaseState {
-    pub fn widget(&self) -> SqliteDatabaseStateWidget {
+    pub fn widget(&self) -> SqliteDatabaseStateWidget<'_> {
         let state = self;
         return SqliteDatabaseStateWidget {
             database_state: state,
@@ -38,36 +111,50 @@ impl<'a> Widget for SqliteDatabaseStateWidget<'a> {
         //Table list system
         let mut list_block = main_block.clone();
         if let SqliteDatabaseStateMode::TABLE_SELECTION = self.database_state.mode {
-            list_block = list_block.red();
+            list_block = list_block
+                .red()
+                .title_bottom(Line::raw(" i: schema | Ins: insert ").right_aligned());
         }
-        let list = List::new(self.database_state.tables.iter().enumerate().map(|(i, t)| {
-            let mut text = Text::raw(t.name.as_str()).centered();
-
-            if let Some(selected) = self.database_state.selected_table {
-                if selected == i {
-                    text = text.style(Style::new().reversed());
-
-                    match self.database_state.mode {
-                        SqliteDatabaseStateMode::QUERY_TOOL => text = text.red(),
-                        _ => (),
-                    }
-                }
-            }
-
-            return text;
+        let tree = &self.database_state.object_tree;
+        let list = List::new(tree.rows().into_iter().map(|row| {
+            let marker = match row.expanded {
+                Some(true) => "▾ ",
+                Some(false) => "▸ ",
+                None => "",
+            };
+            let text = Text::raw(format!("{}{}{}", "  ".repeat(row.depth), marker, row.label));
+            return match row.node {
+                ObjectTreeNode::OBJECT(_) => text,
+                _ => text.bold(),
+            };
         }))
+        .highlight_style(
+            if let SqliteDatabaseStateMode::QUERY_TOOL = self.database_state.mode {
+                Style::new().reversed().red()
+            } else {
+                Style::new().reversed()
+            },
+        )
         .reset()
         .block(list_block);
-        Widget::render(list, left_layout[0], buf);
+        let mut list_state = ListState::default().with_selected(tree.selected);
+        StatefulWidget::render(list, left_layout[0], buf, &mut list_state);
 
         //Table options
-        let options_layout =
-            Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).split(left_layout[1]);
+        let options_layout = Layout::horizontal([
+            Constraint::Fill(2),
+            Constraint::Fill(2),
+            Constraint::Fill(3),
+        ])
+        .split(left_layout[1]);
         let mut new_table_block = main_block.clone();
-        if let Some(option) = self.database_state.selected_table_option {
-            if option == TableOption::CREATE as usize {
-                new_table_block = new_table_block.red();
-            }
+        if self.database_state.selected_table_option == Some(TableOption::CREATE as usize)
+            || matches!(
+                self.database_state.mode,
+                SqliteDatabaseStateMode::TABLE_CREATION
+            )
+        {
+            new_table_block = new_table_block.red();
         }
         let new_table_button = Paragraph::new("Create")
             .centered()
@@ -75,18 +162,85 @@ impl<'a> Widget for SqliteDatabaseStateWidget<'a> {
             .bold();
         Widget::render(new_table_button, options_layout[0], buf);
 
+        let mut import_block = main_block.clone();
+        if self.database_state.selected_table_option == Some(TableOption::IMPORT as usize)
+            || matches!(self.database_state.mode, SqliteDatabaseStateMode::IMPORT)
+        {
+            import_block = import_block.red();
+        }
+        let import_button = Paragraph::new("Import")
+            .centered()
+            .block(import_block)
+            .bold();
+        Widget::render(import_button, options_layout[1], buf);
+
         let mut custom_block = main_block.clone();
-        if let Some(option) = self.database_state.selected_table_option {
-            if option == TableOption::CUSTOM as usize {
-                custom_block = custom_block.red();
-            }
+        if self.database_state.selected_table_option == Some(TableOption::CUSTOM as usize) {
+            custom_block = custom_block.red();
         }
         let custom_button = Paragraph::new("Query Tool")
             .centered()
             .block(custom_block)
             .bold();
 
-        Widget::render(custom_button, options_layout[1], buf);
+        Widget::render(custom_button, options_layout[2], buf);
+
+        //Table creation
+        if let SqliteDatabaseStateMode::TABLE_CREATION = self.database_state.mode
+            && let Some(builder) = &self.database_state.table_builder
+        {
+            TableBuilderWidget {
+                builder,
+                error: self.database_state.error.as_ref(),
+            }
+            .render(main_layout[1], buf);
+            return;
+        }
+
+        //File import
+        if let SqliteDatabaseStateMode::IMPORT = self.database_state.mode
+            && let Some(dialog) = &self.database_state.import_dialog
+        {
+            ImportDialogWidget {
+                dialog,
+                error: self.database_state.error.as_ref(),
+            }
+            .render(main_layout[1], buf);
+            return;
+        }
+
+        //Row insertion
+        if let SqliteDatabaseStateMode::ROW_INSERT = self.database_state.mode
+            && let Some(form) = &self.database_state.row_form
+        {
+            RowFormWidget {
+                form,
+                error: self.database_state.error.as_ref(),
+            }
+            .render(main_layout[1], buf);
+            return;
+        }
+
+        //Schema inspector
+        if let SqliteDatabaseStateMode::SCHEMA_VIEW = self.database_state.mode
+            && let Some(schema) = &self.database_state.schema
+        {
+            SchemaViewWidget {
+                database_state: self.database_state,
+                schema,
+            }
+            .render(main_layout[1], buf);
+            return;
+        }
+
+        //History search
+        if let SqliteDatabaseStateMode::HISTORY_SEARCH = self.database_state.mode {
+            HistoryWidget {
+                database_state: self.database_state,
+            }
+            .render(main_layout[1], buf);
+            return;
+        }
 
         //Query system
         let query_layout = Layout::vertical([Constraint::Percentage(30), Constraint::Fill(1)])
@@ -96,134 +250,142 @@ impl<'a> Widget for SqliteDatabaseStateWidget<'a> {
             query_block = query_block.red();
         }
 
-        let query_tokens = match Tokenizer::new(
-            &SQLiteDialect {},
-            &self.database_state.current_query,
-        )
-        .tokenize()
-        {
-            Ok(tokens) => tokens,
-            Err(_) => {
-                // Fallback: just return everything as raw text
-                vec![Token::Word(sqlparser::tokenizer::Word {
-                    value: self.database_state.current_query.to_string(),
-                    quote_style: None,
-                    keyword: Keyword::NoKeyword,
-                })]
-            }
-        };
+        let (cursor_column, cursor_row) = self.database_state.current_query_cursor;
+        let inner_height = query_layout[0].height.saturating_sub(2) as usize;
+        let inner_width = query_layout[0].width.saturating_sub(4) as usize;
+        let scroll = (
+            (cursor_row + 1).saturating_sub(inner_height) as u16,
+            (cursor_column + 1).saturating_sub(inner_width) as u16,
+        );
+        query_block = query_block.title_bottom(
+            Line::raw(format!(
+                " Ln {}, Col {} | F5/Ctrl+Enter: execute | F4: plan | Ctrl+P/N: history | Ctrl+R: search history ",
+                cursor_row + 1,
+                cursor_column + 1
+            ))
+            .right_aligned(),
+        );
 
-        let mut column = 0;
-        let query_text: Vec<Span> = query_tokens
-            .iter()
-            .enumerate()
-            .flat_map(|(i, t)| {
-                let len = t.to_string().chars().count();
-                let mut span = [match t {
-                    Token::Word(w) => {
-                        if let Keyword::NoKeyword = w.keyword {
-                            if let Some(_) = self.database_state.tables.iter().find(|table| {
-                                table.name.to_lowercase() == t.to_string().to_lowercase()
-                            }) {
-                                Span::raw(t.to_string()).red()
-                            } else {
-                                Span::raw(&w.value)
-                            }
-                        } else {
-                            Span::raw(&w.value).blue()
-                        }
+        let statements = split_statements(&self.database_state.current_query).len();
+        if statements > 1 {
+            let options = &self.database_state.script_options;
+            query_block = query_block.title(
+                Line::raw(format!(
+                    " Script: {} statements | Ctrl+E: {} | Ctrl+T: {} ",
+                    statements,
+                    if options.stop_on_error {
+                        "stop on error"
+                    } else {
+                        "continue on error"
+                    },
+                    if options.single_transaction {
+                        "single transaction"
+                    } else {
+                        "autocommit each"
                     }
-                    _ => Span::from(t.to_string()),
-                }]
-                .to_vec();
-                let (col, row) = self.database_state.current_query_cursor;
-                let inner_span = span[0].clone();
-                if col >= column && col < column + len {
-                    // the cursor should be in this token
-                    let style = inner_span.style;
-                    let content = inner_span.content.clone();
-                    let (left, right) = content.split_at(col - column);
-                    let (mid, right) = right.split_at(1);
-                    let left_styled = Span::raw(left.to_owned()).style(style.clone());
-                    let right_styled = Span::raw(right.to_owned()).style(style.clone());
-                    let mid_styled = Span::raw(mid.to_owned()).reset().reversed();
-                    span = [left_styled, mid_styled, right_styled].to_vec();
-                }
-                column += len;
-                return span;
-            })
-            .chain(
-                if self.database_state.current_query_cursor.0
-                    == self.database_state.current_query.chars().count()
-                {
-                    let mut extra = Vec::new();
-                    extra.push(Span::raw("█").reset());
-                    extra
-                } else {
-                    [].to_vec()
-                },
-            )
-            .collect();
+                ))
+                .right_aligned(),
+            );
+        }
 
-        Paragraph::new(Line::from(query_text))
+        Paragraph::new(self.highlighted_query())
             .reset()
+            .scroll(scroll)
             .block(query_block)
             .render(query_layout[0], buf);
+        if let SqliteDatabaseStateMode::QUERY_PLAN = self.database_state.mode
+            && let Some(plan) = &self.database_state.query_plan
+        {
+            QueryPlanWidget { plan }.render(query_layout[1], buf);
+        } else if let SqliteDatabaseStateMode::EXPORT = self.database_state.mode
+            && let Some(dialog) = &self.database_state.export_dialog
+        {
+            ExportDialogWidget {
+                dialog,
+                error: self.database_state.error.as_ref(),
+            }
+            .render(query_layout[1], buf);
+        } else if let SqliteDatabaseStateMode::PARAMETERS = self.database_state.mode
+            && let Some(dialog) = &self.database_state.parameter_dialog
+        {
+            ParameterDialogWidget {
+                dialog,
+                error: self.database_state.error.as_ref(),
+            }
+            .render(query_layout[1], buf);
+        } else {
+            self.render_result(query_layout[1], buf);
+        }
+
+        //Completion popup, drawn last so it can overlap the result area
+        if let Some(popup) = &self.database_state.completion {
+            let inner = query_block_inner(query_layout[0]);
+            let x = inner.x + (cursor_column as u16).saturating_sub(scroll.1);
+            let y = inner.y + (cursor_row as u16).saturating_sub(scroll.0) + 1;
+            CompletionPopupWidget { popup }.render(Rect::new(x, y, 1, 1), buf);
+        }
+    }
+}
+
+impl<'a> SqliteDatabaseStateWidget<'a> {
+    fn render_result(&self, area: Rect, buf: &mut Buffer) {
+        let area = match &self.database_state.script_report {
+            Some(report) if self.database_state.running_query.is_none() => {
+                let lines = script_lines(report);
+                let height = (lines.len() as u16).min(area.height / 2);
+                let layout =
+                    Layout::vertical([Constraint::Length(height + 1), Constraint::Fill(1)])
+                        .split(area);
+                Paragraph::new(lines).render(layout[0], buf);
+                layout[1]
+            }
+            _ => area,
+        };
+        if let Some(running) = &self.database_state.running_query {
+            let elapsed = running.started.elapsed();
+            let frame = SPINNER[(elapsed.as_millis() / 100) as usize % SPINNER.len()];
+            Line::from(vec![
+                Span::raw(format!("{} ", frame)).yellow(),
+                Span::raw(format!(
+                    "{}… {:.1}s",
+                    running.activity,
+                    elapsed.as_secs_f64()
+                )),
+                Span::raw(" | Esc/Ctrl+C: cancel").dark_gray(),
+            ])
+            .render(area, buf);
+            return;
+        }
+        if let Some(AppError::INTERRUPTED) = &self.database_state.error {
+            Line::from(vec![
+                Span::raw("CANCELLED").yellow(),
+                Span::raw(": the query was interrupted"),
+            ])
+            .render(area, buf);
+            return;
+        }
         if let Some(err) = &self.database_state.error {
             Line::from(vec![
                 Span::raw("ERROR").red(),
                 Span::raw(": "),
                 Span::raw(err.to_string()),
             ])
-            .render(query_layout[1], buf);
+            .render(area, buf);
             return;
         }
         if let Some(queried) = &self.database_state.queried_table_state {
+            let layout = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).split(area);
+            let area = layout[0];
+            stats_line(queried).render(layout[1], buf);
             if let Some(affected) = queried.rows_affected {
                 // just show changed rows:
-                Paragraph::new(format!("QUERY OK: {} rows affected", affected))
-                    .render(query_layout[1], buf);
-            } else if queried.rows.len() > 0 {
-                // show table
-                const max_spacing: u8 = 30;
-                let rows: Vec<Row> = queried
-                    .rows
-                    .iter()
-                    .map(|map| {
-                        let string_row = map.iter().map(|(k, v)| {
-                            let content = match v {
-                                rusqlite::types::Value::Null => "NULL".to_string(),
-                                rusqlite::types::Value::Integer(i) => i.to_string(),
-                                rusqlite::types::Value::Real(f) => f.to_string(),
-                                rusqlite::types::Value::Text(s) => s.to_string(),
-                                rusqlite::types::Value::Blob(items) => "$$BIN$$".to_string(),
-                            };
-                            return format!(
-                                "{:<width$}|",
-                                content,
-                                width = (max_spacing - 1) as usize
-                            );
-                        });
-                        return string_row;
-                    })
-                    .map(|cells| Row::new(cells))
-                    .collect();
-                let header_row: Vec<&str> = queried
-                    .rows
-                    .first()
-                    .unwrap()
-                    .keys()
-                    .map(|s| s.as_str())
-                    .collect();
-
-                let constraints = [Constraint::Max(max_spacing as u16)].repeat(rows.len());
-                Widget::render(
-                    Table::new(rows, constraints)
-                        .header(Row::new(header_row).style(Style::new().reversed()))
-                        .block(main_block.padding(Padding::uniform(1))),
-                    query_layout[1],
-                    buf,
-                );
+                Paragraph::new(format!("QUERY OK: {} rows affected", affected)).render(area, buf);
+            } else if queried.row_count() > 0 {
+                ResultGridWidget {
+                    database_state: self.database_state,
+                    result: queried,
+                }
+                .render(area, buf);
             } else {
                 Line::from(
                     [
@@ -233,30 +395,103 @@ impl<'a> Widget for SqliteDatabaseStateWidget<'a> {
                     ]
                     .to_vec(),
                 )
-                .render(query_layout[1], buf);
+                .render(area, buf);
             }
         }
     }
-}
 
-fn get_last_non_whitespace_token(tokens: &[Token], column: usize) -> Option<(usize, &Token)> {
-    if tokens.len() == 0 {
-        return None;
-    }
-    let mut i = 0;
-    let mut last_non_whitespace = None;
-    let mut current_column = 0;
-    while current_column <= column {
-        let token = &tokens[i];
+    /// Splits `current_query` into lines with syntax highlighting and the
+    /// cursor drawn in. Token spans are used for styling so tokens covering
+    /// several lines (comments, strings) keep their colour on every line.
+    fn highlighted_query(&self) -> Vec<Line<'static>> {
+        let query = &self.database_state.current_query;
+        let mut styles: Vec<Vec<Style>> = query
+            .split('\n')
+            .map(|line| vec![Style::new(); line.chars().count()])
+            .collect();
 
-        if let Token::Whitespace(_) = token {
-            i += 1;
-            current_column += token.to_string().chars().count();
-            continue;
+        // Tokens before a tokenizer error (e.g. an unterminated string while
+        // typing) are still highlighted
+        let mut tokens = Vec::new();
+        let _ =
+            Tokenizer::new(&SQLiteDialect {}, query).tokenize_with_location_into_buf(&mut tokens);
+        for token in tokens {
+            let style = match &token.token {
+                Token::Word(w) => {
+                    if let Keyword::NoKeyword = w.keyword {
+                        if self
+                            .database_state
+                            .tables
+                            .iter()
+                            .any(|table| table.name.to_lowercase() == w.value.to_lowercase())
+                        {
+                            Style::new().red()
+                        } else {
+                            continue;
+                        }
+                    } else {
+                        Style::new().blue()
+                    }
+                }
+                _ => continue,
+            };
+            let (start, end) = (token.span.start, token.span.end);
+            for line in start.line..=end.line {
+                let Some(line_styles) = styles.get_mut(line as usize - 1) else {
+                    continue;
+                };
+                let from = if line == start.line {
+                    start.column as usize - 1
+                } else {
+                    0
+                };
+                let to = if line == end.line {
+                    (end.column as usize - 1).min(line_styles.len())
+                } else {
+                    line_styles.len()
+                };
+                for char_style in line_styles.iter_mut().take(to).skip(from) {
+                    *char_style = style;
+                }
+            }
         }
-        i += 1;
-        current_column += token.to_string().chars().count();
-        last_non_whitespace = Some((i, token));
+
+        let (cursor_column, cursor_row) = self.database_state.current_query_cursor;
+        return query
+            .split('\n')
+            .zip(styles)
+            .enumerate()
+            .map(|(row, (line, line_styles))| {
+                let mut spans: Vec<Span> = Vec::new();
+                let mut current = String::new();
+                let mut current_style = Style::new();
+                for (column, (c, style)) in line.chars().zip(line_styles).enumerate() {
+                    let is_cursor = row == cursor_row && column == cursor_column;
+                    if (style != current_style || is_cursor) && !current.is_empty() {
+                        spans.push(Span::styled(std::mem::take(&mut current), current_style));
+                    }
+                    if is_cursor {
+                        spans.push(Span::raw(c.to_string()).reset().reversed());
+                        continue;
+                    }
+                    current_style = style;
+                    current.push(c);
+                }
+                if !current.is_empty() {
+                    spans.push(Span::styled(current, current_style));
+                }
+                if row == cursor_row && cursor_column >= line.chars().count() {
+                    spans.push(Span::raw("█").reset());
+                }
+                return Line::from(spans);
+            })
+            .collect();
     }
-    return last_non_whitespace;
+}
+
+/// Area inside the bordered, horizontally padded query block.
+fn query_block_inner(area: Rect) -> Rect {
+    return Block::bordered()
+        .padding(Padding::horizontal(1))
+        .inner(area);
 }
@@ -0,0 +1,76 @@
+use ratatui::{
+    layout::{Constraint, Flex, Layout},
+    prelude::{Buffer, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Clear, Padding, Paragraph, Widget},
+};
+
+use crate::data::{
+    row_editor::{DeleteConfirmation, MAX_LISTED_KEYS},
+    sqlite_query::display_value,
+};
+
+/// Dialog listing the keys of the rows about to be deleted, centered in `area`.
+pub struct DeleteConfirmationWidget<'a> {
+    pub confirmation: &'a DeleteConfirmation,
+}
+
+impl<'a> Widget for DeleteConfirmationWidget<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let confirmation = self.confirmation;
+        let count = confirmation.keys.len();
+        let mut lines: Vec<Line> = confirmation
+            .keys
+            .iter()
+            .take(MAX_LISTED_KEYS)
+            .map(|key| {
+                let pairs: Vec<String> = confirmation
+                    .key_names
+                    .iter()
+                    .zip(key)
+                    .map(|(name, value)| format!("{} = {}", name, display_value(value)))
+                    .collect();
+                return Line::raw(pairs.join(", "));
+            })
+            .collect();
+        if count > MAX_LISTED_KEYS {
+            lines.push(Line::raw(format!("… and {} more", count - MAX_LISTED_KEYS)).dark_gray());
+        }
+        lines.push(Line::raw(""));
+        lines.push(Line::raw("Enter/y: delete | Esc/n: cancel").centered());
+
+        let width = lines
+            .iter()
+            .map(|l| l.width() as u16)
+            .max()
+            .unwrap_or(0)
+            .max(40)
+            + 4;
+        let height = lines.len() as u16 + 2;
+        let [area] = Layout::horizontal([Constraint::Length(width)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [area] = Layout::vertical([Constraint::Length(height)])
+            .flex(Flex::Center)
+            .areas(area);
+
+        Clear.render(area, buf);
+        Paragraph::new(lines)
+            .block(
+                Block::bordered()
+                    .padding(Padding::horizontal(1))
+                    .title(format!(
+                        "Delete {} row{} from {}?",
+                        count,
+                        if count == 1 { "" } else { "s" },
+                        confirmation.table
+                    ))
+                    .red(),
+            )
+            .render(area, buf);
+    }
+}
@@ -0,0 +1,79 @@
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::Stylize,
+    text::{Line, Span},
+    widgets::{Block, Padding, Paragraph, Widget},
+};
+
+use crate::{
+    data::result_export::{ExportDialog, ExportField},
+    error::AppError,
+};
+
+pub struct ExportDialogWidget<'a> {
+    pub dialog: &'a ExportDialog,
+    pub error: Option<&'a AppError>,
+}
+
+impl<'a> Widget for ExportDialogWidget<'a> {
+    fn render(self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer)
+    where
+        Self: Sized,
+    {
+        let layout = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).split(area);
+        let dialog = self.dialog;
+        let lines: Vec<Line> = dialog
+            .fields()
+            .into_iter()
+            .map(|field| {
+                let focused = field == dialog.focus;
+                let (label, value) = match field {
+                    ExportField::PATH => ("Path", format!("{}{}", dialog.path, cursor(focused))),
+                    ExportField::FORMAT => ("Format", choice(&format!("{:?}", dialog.format))),
+                    ExportField::BLOBS => ("Blobs", choice(&format!("{:?}", dialog.options.blobs))),
+                    ExportField::TABLE => (
+                        "Insert into",
+                        format!("{}{}", dialog.options.table, cursor(focused)),
+                    ),
+                };
+                let line = Line::from(vec![
+                    Span::raw(format!("{:<12}", label)).bold(),
+                    Span::raw(value),
+                ]);
+                if focused {
+                    return line.reversed();
+                }
+                return line;
+            })
+            .collect();
+        Paragraph::new(lines)
+            .block(
+                Block::bordered()
+                    .padding(Padding::horizontal(1))
+                    .title("Export result")
+                    .red(),
+            )
+            .render(layout[0], buf);
+
+        if let Some(err) = self.error {
+            Line::from(vec![
+                Span::raw("ERROR").red(),
+                Span::raw(": "),
+                Span::raw(err.to_string()),
+            ])
+            .render(layout[1], buf);
+        } else {
+            Line::raw("Enter: export | Up/Down: field | Left/Right: change | Esc: cancel")
+                .centered()
+                .render(layout[1], buf);
+        }
+    }
+}
+
+fn cursor(focused: bool) -> &'static str {
+    return if focused { "█" } else { "" };
+}
+
+fn choice(value: &str) -> String {
+    return format!("◂ {} ▸", value);
+}
@@ -0,0 +1,104 @@
+use humantime::format_duration;
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, ListState, Padding, Paragraph, StatefulWidget, Widget, Wrap},
+};
+use time_humanize::HumanTime;
+
+use crate::data::sqlite_database::SqliteDatabaseState;
+
+pub struct HistoryWidget<'a> {
+    pub database_state: &'a SqliteDatabaseState,
+}
+
+impl<'a> Widget for HistoryWidget<'a> {
+    fn render(self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer)
+    where
+        Self: Sized,
+    {
+        let layout = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Fill(1),
+            Constraint::Percentage(30),
+        ])
+        .split(area);
+        let block = Block::bordered().padding(Padding::horizontal(1));
+
+        Paragraph::new(format!("{}█", self.database_state.history_search))
+            .block(block.clone().title("Search history").red())
+            .render(layout[0], buf);
+
+        let history = &self.database_state.history;
+        let matches = self.database_state.history_matches();
+        let items = matches.iter().map(|index| {
+            let entry = &history.entries[*index];
+            let status = if entry.is_success() {
+                Span::raw("OK   ").green()
+            } else {
+                Span::raw("ERR  ").red()
+            };
+            let rows = match entry.rows {
+                Some(rows) => format!("{} rows", rows),
+                None => String::new(),
+            };
+            let first_line = entry.query.lines().next().unwrap_or("");
+            let more = if entry.query.lines().count() > 1 {
+                " …"
+            } else {
+                ""
+            };
+            return Line::from(vec![
+                status,
+                Span::raw(format!(
+                    "{:<16} {:>10} {:>12}  ",
+                    HumanTime::from(entry.executed_at).to_string(),
+                    format_duration(std::time::Duration::from_millis(
+                        entry.duration.as_millis() as u64
+                    ))
+                    .to_string(),
+                    rows
+                ))
+                .dark_gray(),
+                Span::raw(format!("{}{}", first_line, more)),
+            ]);
+        });
+        let mut list_state = ListState::default().with_selected(if matches.is_empty() {
+            None
+        } else {
+            Some(self.database_state.history_selected)
+        });
+        StatefulWidget::render(
+            List::new(items)
+                .highlight_style(Style::new().reversed())
+                .block(block.clone().title(format!(
+                    "{} of {} queries | Enter: use | Esc: back",
+                    matches.len(),
+                    history.entries.len()
+                ))),
+            layout[1],
+            buf,
+            &mut list_state,
+        );
+
+        let selected = matches
+            .get(self.database_state.history_selected)
+            .map(|index| &history.entries[*index]);
+        let mut preview = Vec::new();
+        if let Some(entry) = selected {
+            preview.extend(entry.query.lines().map(Line::raw));
+            if let Some(error) = &entry.error {
+                preview.push(Line::from(vec![
+                    Span::raw("ERROR").red(),
+                    Span::raw(": "),
+                    Span::raw(error.as_str()),
+                ]));
+            }
+        }
+        Paragraph::new(preview)
+            .wrap(Wrap { trim: false })
+            .block(block.title("Query"))
+            .render(layout[2], buf);
+    }
+}
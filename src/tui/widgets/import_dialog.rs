@@ -0,0 +1,154 @@
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Gauge, Padding, Paragraph, Widget},
+};
+
+use crate::{
+    data::table_import::{ImportDialog, ImportField},
+    error::AppError,
+};
+
+pub struct ImportDialogWidget<'a> {
+    pub dialog: &'a ImportDialog,
+    pub error: Option<&'a AppError>,
+}
+
+impl<'a> Widget for ImportDialogWidget<'a> {
+    fn render(self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer)
+    where
+        Self: Sized,
+    {
+        let dialog = self.dialog;
+        let layout = Layout::vertical([
+            Constraint::Length(5),
+            Constraint::Length(if dialog.running || dialog.done { 3 } else { 0 }),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .split(area);
+
+        let editable = !(dialog.running || dialog.done);
+        let fields: Vec<Line> = [ImportField::PATH, ImportField::FORMAT, ImportField::TABLE]
+            .into_iter()
+            .map(|field| {
+                let focused = editable && field == dialog.focus;
+                let (label, value) = match field {
+                    ImportField::PATH => ("File", format!("{}{}", dialog.path, cursor(focused))),
+                    ImportField::FORMAT => (
+                        "Format",
+                        match dialog.format {
+                            Some(format) => format!("◂ {:?} ▸", format),
+                            None => format!("◂ auto ({:?}) ▸", dialog.effective_format()),
+                        },
+                    ),
+                    ImportField::TABLE if dialog.table.is_empty() && !focused => {
+                        ("Table", dialog.effective_table())
+                    }
+                    ImportField::TABLE => ("Table", format!("{}{}", dialog.table, cursor(focused))),
+                };
+                let line = Line::from(vec![
+                    Span::raw(format!("{:<8}", label)).bold(),
+                    Span::raw(value),
+                ]);
+                if focused {
+                    return line.reversed();
+                }
+                return line;
+            })
+            .collect();
+        Paragraph::new(fields)
+            .block(
+                Block::bordered()
+                    .padding(Padding::horizontal(1))
+                    .title("Import file")
+                    .red(),
+            )
+            .render(layout[0], buf);
+
+        let Some(import) = &dialog.import else {
+            self.render_hint(
+                "Enter: read file | Up/Down: field | Left/Right: format | Esc: cancel",
+                layout[3],
+                buf,
+            );
+            return;
+        };
+
+        if dialog.running || dialog.done {
+            Gauge::default()
+                .block(Block::bordered().title("Progress"))
+                .gauge_style(Style::new().green())
+                .ratio(import.progress().clamp(0.0, 1.0))
+                .label(format!(
+                    "{}/{} rows",
+                    import.next_row,
+                    import.data.rows.len()
+                ))
+                .render(layout[1], buf);
+        }
+
+        let mut lines: Vec<Line> = import.plan().into_iter().map(Line::raw).collect();
+        if dialog.done {
+            lines.push(Line::raw(import.summary()).bold());
+        }
+        if !import.errors.is_empty() {
+            lines.push(Line::raw(format!("Rows not imported ({}):", import.errors.len())).red());
+            let room = (layout[2].height as usize).saturating_sub(lines.len() + 1);
+            let shown = if import.errors.len() > room {
+                room.saturating_sub(1)
+            } else {
+                room
+            };
+            for error in import.errors.iter().take(shown) {
+                lines.push(Line::from(vec![
+                    Span::raw(format!("  row {}: ", error.row)).bold(),
+                    Span::raw(error.message.clone()),
+                ]));
+            }
+            if import.errors.len() > shown {
+                lines.push(Line::raw(format!(
+                    "  … and {} more",
+                    import.errors.len() - shown
+                )));
+            }
+        }
+        Paragraph::new(lines)
+            .block(Block::default().padding(Padding::horizontal(1)))
+            .render(layout[2], buf);
+
+        let hint = if dialog.running {
+            "Esc: stop after this batch"
+        } else if dialog.done {
+            "Enter/Esc: close"
+        } else {
+            "Enter: import | Esc: cancel"
+        };
+        self.render_hint(hint, layout[3], buf);
+    }
+}
+
+impl<'a> ImportDialogWidget<'a> {
+    fn render_hint(
+        &self,
+        hint: &str,
+        area: ratatui::prelude::Rect,
+        buf: &mut ratatui::prelude::Buffer,
+    ) {
+        if let Some(err) = self.error {
+            Line::from(vec![
+                Span::raw("ERROR").red(),
+                Span::raw(": "),
+                Span::raw(err.to_string()),
+            ])
+            .render(area, buf);
+        } else {
+            Line::raw(hint).centered().render(area, buf);
+        }
+    }
+}
+
+fn cursor(focused: bool) -> &'static str {
+    return if focused { "█" } else { "" };
+}
@@ -1 +1,12 @@
+pub mod completion;
 pub mod database_state;
+pub mod delete_confirmation;
+pub mod export_dialog;
+pub mod history;
+pub mod import_dialog;
+pub mod parameter_dialog;
+pub mod query_plan;
+pub mod result_grid;
+pub mod row_form;
+pub mod schema_view;
+pub mod table_builder;
@@ -0,0 +1,86 @@
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Cell, Padding, Row, Table, Widget},
+};
+
+use crate::{
+    data::query_parameters::{ParameterDialog, ParameterKind},
+    error::AppError,
+};
+
+pub struct ParameterDialogWidget<'a> {
+    pub dialog: &'a ParameterDialog,
+    pub error: Option<&'a AppError>,
+}
+
+impl<'a> Widget for ParameterDialogWidget<'a> {
+    fn render(self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer)
+    where
+        Self: Sized,
+    {
+        let layout = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).split(area);
+
+        let header = Row::new(["Parameter", "Type", "Value"]).style(Style::new().reversed());
+        let rows: Vec<Row> = self
+            .dialog
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(index, field)| {
+                let focused = index == self.dialog.focus;
+                let mut value = Line::from(match field.kind {
+                    ParameterKind::NULL => Span::raw("NULL").dark_gray(),
+                    _ => Span::raw(field.text.as_str()),
+                });
+                if focused {
+                    value.push_span(Span::raw("█"));
+                }
+                let row = Row::new([
+                    Cell::new(field.name.as_str()).bold(),
+                    Cell::new(field.kind.label()).blue(),
+                    Cell::new(value),
+                ]);
+                if focused {
+                    return row.reversed();
+                }
+                return row;
+            })
+            .collect();
+        Widget::render(
+            Table::new(
+                rows,
+                [
+                    Constraint::Fill(1),
+                    Constraint::Length(12),
+                    Constraint::Fill(3),
+                ],
+            )
+            .header(header)
+            .block(
+                Block::bordered()
+                    .padding(Padding::horizontal(1))
+                    .title("Query parameters")
+                    .red(),
+            ),
+            layout[0],
+            buf,
+        );
+
+        if let Some(err) = self.error {
+            Line::from(vec![
+                Span::raw("ERROR").red(),
+                Span::raw(": "),
+                Span::raw(err.to_string()),
+            ])
+            .render(layout[1], buf);
+        } else {
+            Line::raw(
+                "Enter: run | Up/Down: parameter | Ctrl+T: type | Ctrl+N: NULL | Esc: cancel",
+            )
+            .centered()
+            .render(layout[1], buf);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use ratatui::{
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Padding, Paragraph, Widget},
+};
+
+use crate::data::query_plan::{BytecodeRow, QueryPlan};
+
+pub struct QueryPlanWidget<'a> {
+    pub plan: &'a QueryPlan,
+}
+
+impl<'a> Widget for QueryPlanWidget<'a> {
+    fn render(self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer)
+    where
+        Self: Sized,
+    {
+        let plan = self.plan;
+        let block = Block::bordered()
+            .padding(Padding::horizontal(1))
+            .title(if plan.show_bytecode {
+                "Bytecode (EXPLAIN)"
+            } else {
+                "Query plan (EXPLAIN QUERY PLAN)"
+            })
+            .title_bottom(
+                Line::raw(if plan.show_bytecode {
+                    " b: query plan | Up/Down/PageUp/PageDown: scroll | Esc: back "
+                } else {
+                    " b: bytecode | Up/Down/PageUp/PageDown: scroll | Esc: back "
+                })
+                .right_aligned(),
+            )
+            .border_style(Style::new().red());
+        let lines = match (&plan.bytecode, plan.show_bytecode) {
+            (Some(bytecode), true) => bytecode_lines(bytecode),
+            _ => {
+                let mut lines = Vec::new();
+                plan_lines(plan, 0, "", &mut lines);
+                if lines.is_empty() {
+                    lines.push(Line::raw("The statement has no query plan").dark_gray());
+                }
+                lines
+            }
+        };
+        let visible = block.inner(area).height as usize;
+        plan.max_scroll.set(lines.len().saturating_sub(visible));
+        Paragraph::new(lines)
+            .scroll((plan.scroll.min(plan.max_scroll.get()) as u16, 0))
+            .block(block)
+            .render(area, buf);
+    }
+}
+
+/// Draws the children of `parent` with tree connectors, depth first.
+fn plan_lines(plan: &QueryPlan, parent: i64, prefix: &str, lines: &mut Vec<Line<'static>>) {
+    let children: Vec<_> = plan.children(parent).collect();
+    for (index, node) in children.iter().enumerate() {
+        let last = index + 1 == children.len();
+        let connector = if last { "└─ " } else { "├─ " };
+        let mut spans = vec![Span::raw(format!("{}{}", prefix, connector)).dark_gray()];
+        spans.extend(detail_spans(&node.detail));
+        lines.push(Line::from(spans));
+        let child_prefix = format!("{}{}", prefix, if last { "   " } else { "│  " });
+        plan_lines(plan, node.id, &child_prefix, lines);
+    }
+}
+
+/// Full scans, temporary b-trees and automatic indexes usually mean a missing
+/// index, searches are what an index gives.
+fn detail_spans(detail: &str) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let rest = if let Some(rest) = detail.strip_prefix("SCAN") {
+        spans.push(Span::raw("SCAN").red().bold());
+        rest
+    } else if let Some(rest) = detail.strip_prefix("SEARCH") {
+        spans.push(Span::raw("SEARCH").green().bold());
+        rest
+    } else {
+        detail
+    };
+    for warning in ["USE TEMP B-TREE", "USING TEMP B-TREE", "AUTOMATIC"] {
+        if let Some((before, after)) = rest.split_once(warning) {
+            spans.push(Span::raw(before.to_string()));
+            spans.push(Span::raw(warning).yellow().bold());
+            spans.push(Span::raw(after.to_string()));
+            return spans;
+        }
+    }
+    spans.push(Span::raw(rest.to_string()));
+    return spans;
+}
+
+fn bytecode_lines(bytecode: &[BytecodeRow]) -> Vec<Line<'static>> {
+    let mut lines = vec![
+        Line::raw(format!(
+            "{:>4}  {:<14} {:>4} {:>4} {:>4}  {:<20} {:>2}  {}",
+            "addr", "opcode", "p1", "p2", "p3", "p4", "p5", "comment"
+        ))
+        .bold(),
+    ];
+    for row in bytecode {
+        lines.push(Line::from(vec![
+            Span::raw(format!("{:>4}  ", row.address)).dark_gray(),
+            Span::raw(format!("{:<14}", row.opcode)).blue(),
+            Span::raw(format!(
+                " {:>4} {:>4} {:>4}  {:<20} {:>2}  {}",
+                row.p1, row.p2, row.p3, row.p4, row.p5, row.comment
+            )),
+        ]));
+    }
+    return lines;
+}
@@ -0,0 +1,160 @@
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Cell, Padding, Row, Table, Widget},
+};
+
+use crate::data::{
+    sqlite_database::{SqliteDatabaseState, SqliteDatabaseStateMode},
+    sqlite_query::{SqliteQueryResult, display_value},
+};
+
+use super::delete_confirmation::DeleteConfirmationWidget;
+
+const COLUMN_WIDTH: u16 = 30;
+
+pub struct ResultGridWidget<'a> {
+    pub database_state: &'a SqliteDatabaseState,
+    pub result: &'a SqliteQueryResult,
+}
+
+impl<'a> Widget for ResultGridWidget<'a> {
+    fn render(self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer)
+    where
+        Self: Sized,
+    {
+        let focused = matches!(
+            self.database_state.mode,
+            SqliteDatabaseStateMode::RESULT_NAVIGATION
+        );
+        let mut block = Block::bordered().padding(Padding::horizontal(1));
+        if focused {
+            block = block.red();
+        }
+        let inner = block.inner(area);
+        block.render(area, buf);
+        let layout = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).split(inner);
+
+        let column_names = self.result.column_names();
+        // one line for the header row
+        let visible_rows = layout[0].height.saturating_sub(1) as usize;
+        let visible_columns = (layout[0].width / COLUMN_WIDTH).max(1) as usize;
+        self.database_state
+            .result_viewport
+            .set((visible_rows, visible_columns));
+
+        let (row_offset, column_offset) = self.database_state.result_scroll;
+        let (cursor_row, cursor_column) = self.database_state.result_cursor;
+        let column_range = column_offset.min(column_names.len())
+            ..(column_offset + visible_columns).min(column_names.len());
+
+        let header =
+            Row::new(column_names[column_range.clone()].to_vec()).style(Style::new().reversed());
+        let rows: Vec<Row> = self
+            .result
+            .window(row_offset, visible_rows.min(u8::MAX as usize) as u8)
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                let row_index = row_offset + i;
+                let cells = column_range.clone().map(|column| {
+                    if let Some(editor) = &self.database_state.cell_editor
+                        && editor.row == row_index
+                        && editor.column == column
+                    {
+                        let content = if editor.null {
+                            "NULL█".to_string()
+                        } else {
+                            format!("{}█", editor.text)
+                        };
+                        return Cell::new(content).yellow().reversed();
+                    }
+                    let pending = self.database_state.pending_edit(row_index, column);
+                    let value = match pending {
+                        Some(edit) => Some(&edit.value),
+                        None => row.get(column),
+                    };
+                    let mut cell = Cell::new(value.map(display_value).unwrap_or_default());
+                    if pending.is_some() {
+                        cell = cell.yellow().italic();
+                    }
+                    if focused && row_index == cursor_row && column == cursor_column {
+                        return cell.reversed();
+                    }
+                    return cell;
+                });
+                let mut row = Row::new(cells);
+                if self.database_state.marked_rows.contains(&row_index) {
+                    row = row.on_dark_gray();
+                }
+                if focused && row_index == cursor_row {
+                    return row.bold();
+                }
+                return row;
+            })
+            .collect();
+
+        let constraints = [Constraint::Length(COLUMN_WIDTH)].repeat(column_range.len());
+        Widget::render(Table::new(rows, constraints).header(header), layout[0], buf);
+
+        let mut status = format!(
+            "Rows {}-{} of {}{} | Columns {}-{} of {}",
+            (row_offset + 1).min(self.result.row_count()),
+            (row_offset + visible_rows).min(self.result.row_count()),
+            self.result.row_count(),
+            if self.result.is_complete() { "" } else { "+" },
+            column_range.start + 1,
+            column_range.end,
+            column_names.len()
+        );
+        if focused && self.database_state.export_notice.is_none() {
+            status = format!(
+                "Row {}, Col {} ({}) | {}",
+                cursor_row + 1,
+                cursor_column + 1,
+                column_names
+                    .get(cursor_column)
+                    .map(|c| c.as_str())
+                    .unwrap_or(""),
+                status
+            );
+            if self.database_state.cell_editor.is_some() {
+                status = format!("Enter: keep | Ctrl+N: NULL | Esc: cancel | {}", status);
+            } else if self.database_state.pending_edits.is_empty() {
+                status = format!(
+                    "Enter: edit | Ins: insert | Space: mark | Del: delete | Ctrl+E: export | {}",
+                    status
+                );
+            }
+        } else if !focused {
+            status.push_str(" | F6: navigate results");
+        }
+        let mut status_line = vec![Span::raw(status)];
+        let pending = self.database_state.pending_edits.len();
+        if pending > 0 {
+            status_line.insert(
+                0,
+                Span::raw(format!(
+                    "{} pending edit{} | Ctrl+S: commit | Ctrl+Z: discard | ",
+                    pending,
+                    if pending == 1 { "" } else { "s" }
+                ))
+                .yellow(),
+            );
+        }
+        if let Some(notice) = &self.database_state.export_notice {
+            status_line.insert(0, Span::raw(format!("{} | ", notice)).green());
+        }
+        if let Some(error) = &self.database_state.edit_error {
+            status_line.insert(0, Span::raw(format!("{} | ", error)).red());
+        }
+        Line::from(status_line)
+            .right_aligned()
+            .render(layout[1], buf);
+
+        if let Some(confirmation) = &self.database_state.delete_confirmation {
+            DeleteConfirmationWidget { confirmation }.render(area, buf);
+        }
+    }
+}
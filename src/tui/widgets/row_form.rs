@@ -0,0 +1,97 @@
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Cell, Padding, Row, Table, Widget},
+};
+
+use crate::{data::row_editor::RowForm, error::AppError};
+
+pub struct RowFormWidget<'a> {
+    pub form: &'a RowForm,
+    pub error: Option<&'a AppError>,
+}
+
+impl<'a> Widget for RowFormWidget<'a> {
+    fn render(self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer)
+    where
+        Self: Sized,
+    {
+        let layout = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).split(area);
+
+        let header = Row::new(["Column", "Type", "Value"]).style(Style::new().reversed());
+        let rows: Vec<Row> = self
+            .form
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(index, field)| {
+                let focused = index == self.form.focus;
+                let mut name = Span::raw(field.column.name.as_str());
+                if field.is_required() {
+                    name = Span::raw(format!("{} *", field.column.name)).bold();
+                }
+                let value = if field.null {
+                    Span::raw("NULL").dark_gray()
+                } else if let Some(placeholder) = &field.placeholder
+                    && field.text.is_empty()
+                {
+                    Span::raw(format!("DEFAULT {}", placeholder)).dark_gray()
+                } else {
+                    Span::raw(field.text.as_str())
+                };
+                let mut value = Line::from(value);
+                if focused {
+                    value.push_span(Span::raw("█"));
+                }
+                let row = Row::new([
+                    Cell::new(name),
+                    Cell::new(Span::raw(format!(
+                        "{} ({:?})",
+                        field.column.declared_type, field.affinity
+                    )))
+                    .blue(),
+                    Cell::new(value),
+                ]);
+                if focused {
+                    return row.reversed();
+                }
+                return row;
+            })
+            .collect();
+        Widget::render(
+            Table::new(
+                rows,
+                [
+                    Constraint::Fill(1),
+                    Constraint::Fill(1),
+                    Constraint::Fill(2),
+                ],
+            )
+            .header(header)
+            .block(
+                Block::bordered()
+                    .padding(Padding::horizontal(1))
+                    .title(format!("Insert into {}", self.form.table))
+                    .red(),
+            ),
+            layout[0],
+            buf,
+        );
+
+        if let Some(err) = self.error {
+            Line::from(vec![
+                Span::raw("ERROR").red(),
+                Span::raw(": "),
+                Span::raw(err.to_string()),
+            ])
+            .render(layout[1], buf);
+        } else {
+            Line::raw(
+                "Enter: insert | Up/Down: field | Ctrl+N: NULL | * required, empty fields are left out | Esc: cancel",
+            )
+            .centered()
+            .render(layout[1], buf);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use ratatui::{
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Padding, Paragraph, Widget},
+};
+use rusqlite::types::Value;
+
+use crate::data::{sqlite_database::SqliteDatabaseState, sqlite_table::TableSchema};
+
+pub struct SchemaViewWidget<'a> {
+    pub database_state: &'a SqliteDatabaseState,
+    pub schema: &'a TableSchema,
+}
+
+impl<'a> Widget for SchemaViewWidget<'a> {
+    fn render(self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer)
+    where
+        Self: Sized,
+    {
+        let schema = self.schema;
+        let block = Block::bordered()
+            .padding(Padding::horizontal(1))
+            .title(if schema.schema == "main" {
+                format!("Schema of {}", schema.name)
+            } else {
+                format!("Schema of {}.{}", schema.schema, schema.name)
+            })
+            .title_bottom(
+                Line::raw(" Up/Down/PageUp/PageDown: scroll | Enter: query table | Esc: back ")
+                    .right_aligned(),
+            )
+            .border_style(Style::new().red());
+        let inner = block.inner(area);
+        // SQL is wrapped here rather than by the paragraph so the number of
+        // lines, and with it the scroll limit, is known
+        let wrap_sql = |sql: &str, indent: usize| -> Vec<String> {
+            let width = (inner.width as usize).saturating_sub(indent).max(1);
+            return sql
+                .lines()
+                .flat_map(|line| {
+                    let chars: Vec<char> = line.chars().collect();
+                    if chars.is_empty() {
+                        return vec![String::new()];
+                    }
+                    return chars
+                        .chunks(width)
+                        .map(|chunk| format!("{}{}", " ".repeat(indent), String::from_iter(chunk)))
+                        .collect();
+                })
+                .collect();
+        };
+        let mut lines: Vec<Line> = Vec::new();
+
+        lines.push(Line::from(vec![
+            Span::raw("Rows: ").bold(),
+            Span::raw(schema.row_count.to_string()),
+        ]));
+        lines.push(Line::raw(""));
+
+        //Columns
+        let name_width = schema
+            .columns
+            .iter()
+            .map(|c| c.name.chars().count())
+            .max()
+            .unwrap_or(0);
+        let type_width = schema
+            .columns
+            .iter()
+            .map(|c| c.declared_type.chars().count())
+            .max()
+            .unwrap_or(0);
+        lines.push(section_title(format!("Columns ({})", schema.columns.len())));
+        for column in &schema.columns {
+            let mut attributes = Vec::new();
+            if column.pk > 0 {
+                attributes.push("PRIMARY KEY".to_string());
+            }
+            if column.not_null {
+                attributes.push("NOT NULL".to_string());
+            }
+            // the default is stored as the SQL text of the expression
+            match &column.default_value {
+                Value::Null => (),
+                Value::Text(s) => attributes.push(format!("DEFAULT {}", s)),
+                Value::Integer(i) => attributes.push(format!("DEFAULT {}", i)),
+                Value::Real(f) => attributes.push(format!("DEFAULT {}", f)),
+                Value::Blob(_) => attributes.push("DEFAULT <blob>".to_string()),
+            }
+            lines.push(Line::from(vec![
+                Span::raw(format!("  {:<width$}  ", column.name, width = name_width)),
+                Span::raw(format!(
+                    "{:<width$}  ",
+                    column.declared_type,
+                    width = type_width
+                ))
+                .blue(),
+                Span::raw(attributes.join(", ")).dark_gray(),
+            ]));
+        }
+        lines.push(Line::raw(""));
+
+        //Indexes
+        lines.push(section_title(format!("Indexes ({})", schema.indexes.len())));
+        for index in &schema.indexes {
+            let kind = match index.origin.as_str() {
+                "pk" => "primary key",
+                "u" => "unique constraint",
+                _ if index.unique => "unique index",
+                _ => "index",
+            };
+            lines.push(Line::from(vec![
+                Span::raw(format!("  {} ", index.name)),
+                Span::raw(format!("({})", index.columns.join(", "))),
+                Span::raw(format!(
+                    "  {}{}",
+                    kind,
+                    if index.partial { ", partial" } else { "" }
+                ))
+                .dark_gray(),
+            ]));
+        }
+        lines.push(Line::raw(""));
+
+        //Foreign keys
+        lines.push(section_title(format!(
+            "Foreign keys ({})",
+            schema.foreign_keys.len()
+        )));
+        for key in &schema.foreign_keys {
+            let from: Vec<&str> = key.columns.iter().map(|(from, _)| from.as_str()).collect();
+            let to: Vec<&str> = key
+                .columns
+                .iter()
+                .filter_map(|(_, to)| to.as_deref())
+                .collect();
+            let target = if to.is_empty() {
+                key.table.clone()
+            } else {
+                format!("{}({})", key.table, to.join(", "))
+            };
+            lines.push(Line::from(vec![
+                Span::raw(format!("  ({}) -> ", from.join(", "))),
+                Span::raw(target).red(),
+                Span::raw(format!(
+                    "  ON UPDATE {} ON DELETE {}",
+                    key.on_update, key.on_delete
+                ))
+                .dark_gray(),
+            ]));
+        }
+        lines.push(Line::raw(""));
+
+        //Triggers
+        lines.push(section_title(format!(
+            "Triggers ({})",
+            schema.triggers.len()
+        )));
+        for trigger in &schema.triggers {
+            lines.push(Line::raw(format!("  {}", trigger.name)));
+            if let Some(sql) = &trigger.sql {
+                lines.extend(
+                    wrap_sql(sql, 4)
+                        .into_iter()
+                        .map(|l| Line::raw(l).dark_gray()),
+                );
+            }
+        }
+        lines.push(Line::raw(""));
+
+        //Original CREATE statement
+        lines.push(section_title("CREATE statement".to_string()));
+        match &schema.create_sql {
+            Some(sql) => lines.extend(wrap_sql(sql, 2).into_iter().map(Line::raw)),
+            None => lines.push(Line::raw("  (not available)").dark_gray()),
+        }
+
+        let max_scroll = lines.len().saturating_sub(inner.height as usize);
+        self.database_state.schema_max_scroll.set(max_scroll);
+        let scroll = self.database_state.schema_scroll.min(max_scroll);
+
+        Paragraph::new(lines)
+            .reset()
+            .scroll((scroll as u16, 0))
+            .block(block)
+            .render(area, buf);
+    }
+}
+
+fn section_title(title: String) -> Line<'static> {
+    return Line::raw(title).bold().underlined();
+}
@@ -0,0 +1,115 @@
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Cell, Padding, Paragraph, Row, Table, Widget, Wrap},
+};
+use strum::EnumCount;
+
+use crate::{
+    data::table_builder::{ColumnField, TableBuilder, TableBuilderFocus},
+    error::AppError,
+};
+
+pub struct TableBuilderWidget<'a> {
+    pub builder: &'a TableBuilder,
+    pub error: Option<&'a AppError>,
+}
+
+impl<'a> Widget for TableBuilderWidget<'a> {
+    fn render(self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer)
+    where
+        Self: Sized,
+    {
+        let layout = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Fill(1),
+            Constraint::Percentage(40),
+            Constraint::Length(1),
+        ])
+        .split(area);
+        let block = Block::bordered();
+
+        //Table name
+        let mut name = Span::raw(self.builder.name.as_str());
+        if let TableBuilderFocus::TableName = self.builder.focus {
+            name = Span::raw(format!("{}█", self.builder.name)).reversed();
+        }
+        Paragraph::new(Line::from(name))
+            .block(block.clone().title("Table name").red())
+            .render(layout[0], buf);
+
+        //Columns
+        let header = Row::new(
+            (0..ColumnField::COUNT)
+                .map(|i| ColumnField::try_from(i).unwrap().label())
+                .collect::<Vec<&str>>(),
+        )
+        .style(Style::new().reversed());
+        let rows: Vec<Row> = self
+            .builder
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| {
+                let cells = (0..ColumnField::COUNT).map(|i| {
+                    let field = ColumnField::try_from(i).unwrap();
+                    let focused = self.builder.focus == TableBuilderFocus::Column(index, field);
+                    let content = if let Some(flag) = column.flag(field) {
+                        if flag { "[x]" } else { "[ ]" }.to_string()
+                    } else if focused {
+                        format!("{}█", column.text(field).unwrap())
+                    } else {
+                        column.text(field).unwrap().clone()
+                    };
+                    let cell = Cell::new(content);
+                    if focused {
+                        return cell.reversed();
+                    }
+                    return cell;
+                });
+                return Row::new(cells);
+            })
+            .collect();
+        let constraints = [
+            Constraint::Fill(2),
+            Constraint::Fill(2),
+            Constraint::Length(8),
+            Constraint::Fill(2),
+            Constraint::Length(4),
+            Constraint::Length(6),
+            Constraint::Fill(3),
+        ];
+        Widget::render(
+            Table::new(rows, constraints).header(header).block(
+                block
+                    .clone()
+                    .title("Columns")
+                    .padding(Padding::horizontal(1)),
+            ),
+            layout[1],
+            buf,
+        );
+
+        //Preview
+        Paragraph::new(self.builder.to_sql())
+            .wrap(Wrap { trim: false })
+            .block(block.title("Preview").padding(Padding::horizontal(1)))
+            .render(layout[2], buf);
+
+        if let Some(err) = self.error {
+            Line::from(vec![
+                Span::raw("ERROR").red(),
+                Span::raw(": "),
+                Span::raw(err.to_string()),
+            ])
+            .render(layout[3], buf);
+        } else {
+            Line::raw(
+                "Enter: create | Tab/Shift+Tab: next/previous field | Up/Down: column | Space: toggle | Ctrl+N: add column | Ctrl+D: remove column | Esc: cancel",
+            )
+            .centered()
+            .render(layout[3], buf);
+        }
+    }
+}